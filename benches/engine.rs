@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Joseph Prichard 2022.
+ */
+
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use othello_engine::agent::{AgentConfig, OthelloAgent};
+use othello_engine::board::{Color, OthelloBoard};
+use othello_engine::command::LEVEL_DEPTHS;
+use othello_engine::eval;
+use othello_engine::hasher::ZHasher;
+
+const HASHER_SEED: u64 = 0x0BE110;
+
+// plays a fixed line out of the opening so every run benchmarks the same positions
+fn positions() -> Vec<(&'static str, OthelloBoard)> {
+    let mut positions = vec![];
+    let mut board = OthelloBoard::new();
+    for ply in 0..=30 {
+        match ply {
+            0 => positions.push(("opening", board)),
+            14 => positions.push(("midgame", board)),
+            30 => positions.push(("late_midgame", board)),
+            _ => {}
+        }
+        let moves = board.find_current_moves_as_vec();
+        board = board.make_move(moves[(ply * 3) % moves.len()]);
+    }
+    positions
+}
+
+fn bench_move_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_potential_moves");
+    for (name, board) in positions() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| {
+                let mut count = 0;
+                board.find_potential_moves(Color::Black, |_| count += 1);
+                black_box(count)
+            })
+        });
+    }
+    group.finish();
+}
+
+// collecting into a smallvec avoids the heap allocation the vec needs for every node
+fn bench_collect_moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect_moves");
+    for (name, board) in positions() {
+        group.bench_with_input(BenchmarkId::new("vec", name), &board, |b, board| {
+            b.iter(|| black_box(board.find_current_moves_as_vec()))
+        });
+        group.bench_with_input(BenchmarkId::new("smallvec", name), &board, |b, board| {
+            b.iter(|| black_box(board.find_current_moves_smallvec()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_make_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_move");
+    for (name, board) in positions() {
+        let mov = board.find_current_moves_as_vec()[0];
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| black_box(board.make_move(black_box(mov))))
+        });
+    }
+    group.finish();
+}
+
+fn bench_heuristic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_heuristic");
+    for (name, board) in positions() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| black_box(eval::find_heuristic(board)))
+        });
+    }
+    group.finish();
+}
+
+// the deepest levels take a long time per search, filter by level to run them selectively
+fn bench_best_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_best_move");
+    group.sample_size(10);
+    for (level, depth) in LEVEL_DEPTHS.iter().enumerate() {
+        for (name, board) in positions() {
+            let id = BenchmarkId::new(format!("level_{}", level + 1), name);
+            group.bench_with_input(id, &board, |b, board| {
+                // a fresh agent per iteration keeps the cache from carrying over between samples
+                b.iter_with_setup(
+                    || OthelloAgent::with_hasher(AgentConfig::new(*depth), ZHasher::from_seed(HASHER_SEED)),
+                    |mut agent| black_box(agent.find_best_move(board))
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_move_generation, bench_collect_moves, bench_make_move, bench_heuristic, bench_best_move);
+criterion_main!(benches);
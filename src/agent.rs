@@ -2,172 +2,1273 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
-use std::time::SystemTime;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use smallvec::SmallVec;
-use crate::board::OthelloBoard;
+use crate::board::{Color, OthelloBoard};
 use crate::eval;
+use crate::errors::{ParseError, ParseResult};
+use crate::eval::{EvalFn, ScoringMode};
 use crate::hasher::ZHasher;
-use crate::cache::{CacheNode, TranspositionTable};
+use crate::logger;
+use crate::cache::{Bound, CacheNode, EvalCache, TranspositionTable};
 use crate::profile::{Profiler, Run};
-use crate::tile::RankedTile;
+use crate::tile::{RankedTile, Tile};
+
+// bounds how many plies past the depth floor the quiescence search may extend
+const MAX_QUIESCENCE_DEPTH: u32 = 4;
+// mixed into the keys of nodes searched with a draw score, odd so that distinct draw scores give distinct keys
+const DRAW_SCORE_KEY_SALT: i64 = 0x9E37_79B9_7F4A_7C15u64 as i64;
+// the side to move having at least this many times the moves of the opponent is a squeeze that can swing the score
+const LOUD_MOBILITY_RATIO: usize = 4;
+// how many nodes are visited between checks of the clock, checking at every node would slow the search down
+const TIME_CHECK_INTERVAL: u64 = 1024;
+// how far a score must lean towards one side before the position is called won or lost, finished games always count
+pub const VERDICT_THRESHOLD: f32 = 300f32;
+// the most plies any line can have, a move for every tile and a pass before each
+const MAX_PLY: u32 = 2 * 64 + 1;
+// explored trees hold every line without pruning, so they grow with the branching factor to the power of the depth
+pub const MAX_EXPLORE_DEPTH: u32 = 4;
+// move sampling is seeded so that games played with a temperature are reproducible
+const RNG_SEED: u64 = 0x5EED;
 
 #[derive(Copy, Clone)]
 pub struct AgentConfig {
     max_search_depth: u32,
+    quiescence: bool,
+    temperature: f32,
+    // when off the transposition table and eval cache are bypassed, so the raw search can be timed
+    cache: bool,
+    // a hard cap on the wall clock time of a search, after which it stops with the best it has found so far
+    time_limit: Option<Duration>,
+    // how much a drawn endgame is worth less than zero to the side searching, so it plays on for a win instead
+    contempt: f32,
+    // scores the positions the search stops at before the endgame
+    eval_fn: EvalFn,
+    // the first iterative deepening depth and how many plies each later iteration goes past the one before, the full
+    // depth is always searched last so a coarse schedule only skips the shallow iterations
+    start_depth: u32,
+    step: u32,
+    // how finished games are counted
+    scoring: ScoringMode,
 }
 
 impl AgentConfig {
     pub fn new(max_search_depth: u32) -> Self {
-        Self { max_search_depth }
+        Self {
+            max_search_depth,
+            quiescence: false,
+            temperature: 0f32,
+            cache: true,
+            time_limit: None,
+            contempt: 0f32,
+            eval_fn: eval::find_heuristic,
+            start_depth: 1,
+            step: 1,
+            scoring: ScoringMode::Strict,
+        }
+    }
+
+    pub fn max_search_depth(&self) -> u32 {
+        self.max_search_depth
+    }
+
+    pub fn cache(&self) -> bool {
+        self.cache
+    }
+
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Option<Duration>) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    pub fn contempt(&self) -> f32 {
+        self.contempt
+    }
+
+    pub fn with_contempt(mut self, contempt: f32) -> Self {
+        self.contempt = contempt;
+        self
+    }
+
+    pub fn with_eval_fn(mut self, eval_fn: EvalFn) -> Self {
+        self.eval_fn = eval_fn;
+        self
+    }
+
+    pub fn scoring(&self) -> ScoringMode {
+        self.scoring
+    }
+
+    pub fn with_scoring(mut self, scoring: ScoringMode) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    pub fn start_depth(&self) -> u32 {
+        self.start_depth
+    }
+
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    pub fn with_start_depth(mut self, start_depth: u32) -> Self {
+        self.start_depth = start_depth.max(1);
+        self
+    }
+
+    pub fn with_step(mut self, step: u32) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    // the depths iterative deepening searches in order, ending on the full depth of the config
+    fn deepening_depths(&self) -> impl Iterator<Item = u32> {
+        let full_depth = self.max_search_depth.saturating_sub(2);
+        let start_depth = self.start_depth.min(full_depth.max(1));
+        (start_depth..full_depth).step_by(self.step as usize).chain((start_depth..=full_depth).rev().take(1))
+    }
+
+    pub fn with_max_search_depth(mut self, max_search_depth: u32) -> Self {
+        self.max_search_depth = max_search_depth;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_quiescence(mut self, quiescence: bool) -> Self {
+        self.quiescence = quiescence;
+        self
+    }
+}
+
+// what a single search should produce, either just the best move or a heuristic for every move
+#[derive(Copy, Clone)]
+pub struct SearchLimits {
+    pub rank_all: bool,
+}
+
+impl SearchLimits {
+    pub fn best() -> Self {
+        Self { rank_all: false }
+    }
+
+    pub fn ranked() -> Self {
+        Self { rank_all: true }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SearchStats {
+    // the deepest iteration any root move was searched to
+    pub depth: u32,
+    pub hits: u32,
+    pub misses: u32,
+    pub nodes: u64,
+    // leaves that had to be statically evaluated rather than answered by the eval cache
+    pub evals: u64,
+    // whether the search was cut short by the time limit
+    pub timed_out: bool,
+    pub time_taken: u128,
+}
+
+#[derive(Clone)]
+pub struct SearchResult {
+    pub best: Option<RankedTile>,
+    // empty unless every move was ranked
+    pub ranked: Vec<RankedTile>,
+    pub stats: SearchStats,
+}
+
+// a position in an explored game tree, the move is none for the root and for a pass
+#[derive(Clone)]
+pub struct TreeNode {
+    pub mov: Option<Tile>,
+    pub board: OthelloBoard,
+    // the minimax score of the position from black's perspective, its static heuristic if it has no children
+    pub score: f32,
+    pub children: Vec<TreeNode>,
+}
+
+// how the position looks for the side to move, so a server can offer a draw or resign on its behalf
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Winning,
+    Losing,
+    Unclear,
+}
+
+impl Verdict {
+    // scores are from black's perspective, so they are flipped when white is the side to move
+    pub fn from_score(score: f32, black_move: bool) -> Self {
+        let score = if black_move { score } else { -score };
+        if score >= VERDICT_THRESHOLD {
+            Verdict::Winning
+        } else if score <= -VERDICT_THRESHOLD {
+            Verdict::Losing
+        } else {
+            Verdict::Unclear
+        }
     }
 }
 
 pub struct OthelloAgent {
     hasher: ZHasher,
     config: AgentConfig,
+    rng: StdRng,
+    // nodes visited and static evaluations computed by the current or most recent search
+    nodes: u64,
+    evals: u64,
+    eval_cache: EvalCache,
+    // set once the search runs past its time limit, every node still being searched then unwinds without a result
+    deadline: Option<Instant>,
+    timed_out: bool,
+    // how many plies deep the search currently is below the root move, and the most a line can have from there
+    ply: u32,
+    max_ply: u32,
+    // the score of an even endgame for this search, the contempt taken away from the side at the root
+    draw_score: f32,
+    // whether bounds in the transposition table narrow the window, rather than only cutting off when they close it
+    narrow_window: bool,
     pub cache: TranspositionTable,
     pub profiler: Profiler,
 }
 
 impl OthelloAgent {
     pub fn new(config: AgentConfig) -> Self {
+        Self::with_hasher(config, ZHasher::new())
+    }
+
+    pub fn with_hasher(config: AgentConfig, hasher: ZHasher) -> Self {
         Self {
             config,
-            hasher: ZHasher::new(),
+            rng: StdRng::seed_from_u64(RNG_SEED),
+            nodes: 0,
+            evals: 0,
+            eval_cache: EvalCache::new(),
+            deadline: None,
+            timed_out: false,
+            ply: 0,
+            max_ply: MAX_PLY,
+            draw_score: 0f32,
+            narrow_window: true,
+            hasher,
             cache: TranspositionTable::new(),
             profiler: Profiler::new(),
         }
     }
 
-    pub fn add_run(&mut self, time_taken: u128) {
+    pub fn set_config(&mut self, config: AgentConfig) {
+        self.config = config;
+    }
+
+    // the key this agent stores the board under in its transposition table, before any draw score is mixed in
+    pub fn hash(&self, board: &OthelloBoard) -> i64 {
+        self.hasher.hash(board)
+    }
+
+    pub fn last_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn add_run(&mut self, stats: &SearchStats) {
         let run = Run::new(
-            self.config.max_search_depth, self.cache.hits(),
-            self.cache.misses(), time_taken
+            self.config.max_search_depth, stats.hits, stats.misses,
+            stats.nodes, stats.evals, stats.time_taken
         );
         self.profiler.add_run(run);
     }
 
-    pub fn find_best_move(&mut self, board: &OthelloBoard) -> Option<RankedTile> {
+    // searches the position and collects everything the search found, callers project out the fields they need
+    pub fn search(&mut self, board: &OthelloBoard, limits: SearchLimits) -> SearchResult {
         let start_time = SystemTime::now();
+        self.deadline = self.config.time_limit.map(|time_limit| Instant::now() + time_limit);
+        self.timed_out = false;
+        // a search that unwound partway never stepped back out of the plies it was in
+        self.ply = 0;
+        self.draw_score = if board.black_move { -self.config.contempt } else { self.config.contempt };
         self.cache.reset_counts();
+        self.nodes = 0;
+        self.evals = 0;
+        // evaluations are only memoized within a search so they never outlive a change to the weights
+        self.eval_cache.clear();
 
+        let (best, ranked) = if limits.rank_all {
+            let ranked = self.rank_children(board);
+            // ranked tiles are sorted so that the best move for the side to move comes last
+            (ranked.last().copied(), ranked)
+        } else {
+            let children = Self::order_children(board, self.config.eval_fn);
+            let best = if children.len() == 1 {
+                // a forced move needs no search, only a heuristic to report along with it
+                let (mov, child) = children[0];
+                let heuristic = self.find_leaf_heuristic(&child);
+                Some(RankedTile::new(mov, heuristic, 0))
+            } else if self.config.temperature > 0f32 {
+                self.sample_children(board, &children)
+            } else {
+                self.search_children(board, &children)
+            };
+            (best, vec![])
+        };
+
+        let tt_stats = self.cache.stats();
+        let stats = SearchStats {
+            depth: ranked.iter().chain(best.iter()).map(|r| r.depth).max().unwrap_or(0),
+            hits: tt_stats.hits,
+            misses: tt_stats.misses,
+            nodes: self.nodes,
+            evals: self.evals,
+            timed_out: self.timed_out,
+            time_taken: SystemTime::now().duration_since(start_time).unwrap().as_millis(),
+        };
+        if stats.timed_out {
+            logger::info(&format!("Search timed out after {} ms, using the best move found so far", stats.time_taken));
+        }
+        logger::debug(&format!(
+            "Searched to depth {}, hits: {}, misses: {}, nodes: {}, evals: {}, time_taken: {} ms",
+            stats.depth, stats.hits, stats.misses, stats.nodes, stats.evals, stats.time_taken
+        ));
+        self.add_run(&stats);
+
+        SearchResult { best, ranked, stats }
+    }
+
+    // finds the move with the best search heuristic for the side to move
+    // moves with equal search heuristics are tie-broken by the order they are searched in: the move whose child
+    // has the better static heuristic wins, and if those are equal too then the move with the lowest tile index wins
+    // with a nonzero temperature the move is instead sampled from the softmax of every move's heuristic
+    pub fn find_best_move(&mut self, board: &OthelloBoard) -> Option<RankedTile> {
+        self.search(board, SearchLimits::best()).best
+    }
+
+    // runs a shallow search that only fills the transposition table, so a deeper search of the position can reuse it
+    // a node is only used by lookups at most as deep as it was searched, so the shallow nodes never stand in for deeper ones
+    pub fn warm(&mut self, board: &OthelloBoard, depth: u32) -> SearchStats {
+        let config = self.config;
+        self.config = config.with_max_search_depth(depth).with_temperature(0f32);
+        let stats = self.search(board, SearchLimits::best()).stats;
+        self.config = config;
+        stats
+    }
+
+    // searches the position and judges it for the side to move, passing first if it has no moves
+    pub fn evaluation_verdict(&mut self, board: &OthelloBoard) -> Verdict {
+        let score = self.evaluate_position(board);
+        Verdict::from_score(score, board.black_move)
+    }
+
+    // the searched score of the position from black's perspective, passing first if the side to move has no moves
+    pub fn evaluate_position(&mut self, board: &OthelloBoard) -> f32 {
+        if board.is_game_over() {
+            eval::find_terminal_score_with(board, self.config.scoring)
+        } else if board.must_pass() {
+            let passed = board.make_pass();
+            self.search(&passed, SearchLimits::ranked()).best.map_or(0f32, |best| best.heuristic)
+        } else {
+            // ranking searches every move fully, even a forced one the best move search would skip
+            self.search(board, SearchLimits::ranked()).best.map_or(0f32, |best| best.heuristic)
+        }
+    }
+
+    // sorts the root moves by the static heuristic of their child, so the most promising move is searched first
+    // ties are broken by tile index so the search order never depends on the order moves are generated in
+    fn order_children(board: &OthelloBoard, eval_fn: EvalFn) -> SmallVec<[(Tile, OthelloBoard); 16]> {
+        let mut children = SmallVec::<[(f32, Tile, OthelloBoard); 16]>::new();
+        for mov in board.find_current_moves_smallvec() {
+            let child = board.make_move(mov);
+            children.push((eval::ensure_finite(eval_fn(&child)), mov, child));
+        }
+        children.sort_by(|a, b| {
+            let ordering = if board.black_move { b.0.total_cmp(&a.0) } else { a.0.total_cmp(&b.0) };
+            ordering.then_with(|| a.1.index().cmp(&b.1.index()))
+        });
+        children.into_iter().map(|(_, mov, child)| (mov, child)).collect()
+    }
+
+    fn search_children(&mut self, board: &OthelloBoard, children: &[(Tile, OthelloBoard)]) -> Option<RankedTile> {
         let mut best_move = None;
-        let mut best_heuristic = if board.black_move { f32::MIN } else { f32::MAX };
+        let mut best_heuristic = if board.black_move { eval::LOSS } else { eval::WIN };
+        let mut best_depth = 0;
 
         // call the iterative deepening minimax to calculate the heuristic for each potential move and determine the best one
-        board.find_current_moves(|mov| {
-            // get the child board for the move and check if it is better than the last one
-            let child = board.make_move(mov);
-            let heuristic = self.evaluate_base(&child);
-            // compare the move to make sure we get the best one
-            if board.black_move {
-                if heuristic > best_heuristic {
-                    best_move = Some(mov);
-                    best_heuristic = heuristic;
-                }
+        for &(mov, child) in children {
+            // once out of time the remaining moves could only be judged statically, so the best so far is kept
+            if self.timed_out && best_move.is_some() {
+                break;
+            }
+            // the best heuristic so far bounds the window, so later children only need to prove they are better
+            let (heuristic, depth) = if board.black_move {
+                self.evaluate_base(&child, best_heuristic, eval::WIN)
             } else {
-                if heuristic < best_heuristic {
+                self.evaluate_base(&child, eval::LOSS, best_heuristic)
+            };
+            // compare the move to make sure we get the best one, the first move is taken even if it is a certain loss
+            if best_move.is_none() {
+                best_move = Some(mov);
+                best_heuristic = heuristic;
+                best_depth = depth;
+            } else if board.black_move {
+                if heuristic > best_heuristic {
                     best_move = Some(mov);
                     best_heuristic = heuristic;
+                    best_depth = depth;
                 }
+            } else if heuristic < best_heuristic {
+                best_move = Some(mov);
+                best_heuristic = heuristic;
+                best_depth = depth;
             }
-        });
+            // a proven win can't be improved on by another move
+            if eval::is_decisive_score(best_heuristic) && (best_heuristic > 0f32) == board.black_move {
+                break;
+            }
+        }
+
+        RankedTile::from_option(best_move, best_heuristic, best_depth)
+    }
+
+    fn sample_children(&mut self, board: &OthelloBoard, children: &[(Tile, OthelloBoard)]) -> Option<RankedTile> {
+        // every move needs an exact heuristic to be weighted, so none of them can be searched with a narrowed window
+        let mut ranked_tiles = SmallVec::<[RankedTile; 16]>::new();
+        for &(mov, child) in children {
+            let (heuristic, depth) = self.evaluate_base(&child, eval::LOSS, eval::WIN);
+            ranked_tiles.push(RankedTile::new(mov, heuristic, depth));
+        }
 
-        let time_taken = SystemTime::now().duration_since(start_time).unwrap().as_millis();
-        self.add_run(time_taken);
+        // scores are taken from the perspective of the side to move and shifted by the best one to keep exp in range
+        let sign = if board.black_move { 1f32 } else { -1f32 };
+        let best_score = ranked_tiles.iter()
+            .map(|r| sign * r.heuristic)
+            .fold(eval::LOSS, f32::max);
+        let weights = ranked_tiles.iter()
+            .map(|r| ((sign * r.heuristic - best_score) / self.config.temperature).exp());
 
-        RankedTile::from_option(best_move, best_heuristic)
+        let distribution = WeightedIndex::new(weights).ok()?;
+        Some(ranked_tiles[distribution.sample(&mut self.rng)])
+    }
+
+    // expands every line from the position down to the depth without pruning or caching, for inspecting what the
+    // search sees rather than for choosing a move
+    pub fn explore(&mut self, board: &OthelloBoard, depth: u32) -> ParseResult<TreeNode> {
+        if depth > MAX_EXPLORE_DEPTH {
+            return Err(ParseError::OutOfRange(format!("Explore depth must be at most {}", MAX_EXPLORE_DEPTH)));
+        }
+        self.draw_score = if board.black_move { -self.config.contempt } else { self.config.contempt };
+        Ok(self.explore_node(None, *board, depth))
+    }
+
+    fn explore_node(&mut self, mov: Option<Tile>, board: OthelloBoard, depth: u32) -> TreeNode {
+        if board.is_game_over() {
+            let score = self.apply_contempt(&board, eval::find_terminal_score_with(&board, self.config.scoring));
+            return TreeNode { mov, board, score, children: vec![] };
+        }
+        if depth == 0 {
+            let score = self.apply_contempt(&board, eval::find_leaf_heuristic_with(&board, self.config.eval_fn, self.config.scoring));
+            return TreeNode { mov, board, score, children: vec![] };
+        }
+
+        let children: Vec<TreeNode> = if board.must_pass() {
+            vec![self.explore_node(None, board.make_pass(), depth - 1)]
+        } else {
+            board.find_current_moves_smallvec()
+                .into_iter()
+                .map(|child_mov| self.explore_node(Some(child_mov), board.make_move(child_mov), depth - 1))
+                .collect()
+        };
+        let scores = children.iter().map(|child| child.score);
+        let score = if board.black_move { scores.fold(eval::LOSS, f32::max) } else { scores.fold(eval::WIN, f32::min) };
+        TreeNode { mov, board, score, children }
     }
 
     pub fn find_ranked_moves(&mut self, board: &OthelloBoard) -> Vec<RankedTile> {
-        let start_time = SystemTime::now();
-        self.cache.reset_counts();
+        self.search(board, SearchLimits::ranked()).ranked
+    }
 
+    fn rank_children(&mut self, board: &OthelloBoard) -> Vec<RankedTile> {
         let mut ranked_tiles = vec![];
         // call the iterative deepening minimax to calculate the heuristic for each potential move
-        board.find_current_moves(|mov| {
-            // get the child board for the move and check if it is better than the last one
-            let child = board.make_move(mov);
-            let heuristic = self.evaluate_base(&child);
-            ranked_tiles.push(RankedTile::new(mov, heuristic))
+        // the children are visited in the order the best move search uses, which is kept to break ties
+        for (order, (mov, child)) in Self::order_children(board, self.config.eval_fn).into_iter().enumerate() {
+            let (heuristic, depth) = self.evaluate_base(&child, eval::LOSS, eval::WIN);
+            ranked_tiles.push((order, RankedTile::new(mov, heuristic, depth)))
+        }
+
+        // tied moves put the one searched first last, as the best move search keeps the first of equals
+        ranked_tiles.sort_by(|(a_order, a), (b_order, b)| {
+            let ordering = if board.black_move { a.heuristic.total_cmp(&b.heuristic) } else { b.heuristic.total_cmp(&a.heuristic) };
+            ordering.then_with(|| b_order.cmp(a_order))
         });
 
-        if board.black_move {
-            ranked_tiles.sort_by(|a, b| {
-                a.heuristic.total_cmp(&b.heuristic)
-            });
-        } else {
-            ranked_tiles.sort_by(|a, b| {
-                b.heuristic.total_cmp(&a.heuristic)
-            });
+        ranked_tiles.into_iter().map(|(_, ranked)| ranked).collect()
+    }
+
+    // returns the heuristic from the deepest iteration along with the depth it was searched to
+    // an iteration cut short by the time limit is thrown away, falling back to the static heuristic if none finished
+    fn evaluate_base(&mut self, board: &OthelloBoard, alpha: f32, beta: f32) -> (f32, u32) {
+        let mut heuristic = self.apply_contempt(board, eval::find_leaf_heuristic_with(board, self.config.eval_fn, self.config.scoring));
+        let mut depth = 0;
+        // every move fills a tile and no two passes are made in a row, so a line can't outrun twice the empty tiles
+        self.max_ply = 2 * board.count_tiles(Color::Empty) as u32 + 1;
+        for depth_limit in self.config.deepening_depths() {
+            let iteration_heuristic = self.evaluate(*board, depth_limit, board.black_move, alpha, beta, false);
+            if self.timed_out {
+                break;
+            }
+            heuristic = iteration_heuristic;
+            depth = depth_limit;
+            // the game was played out to the end, so searching deeper can't change who wins
+            if eval::is_decisive_score(heuristic) {
+                break;
+            }
         }
+        (heuristic, depth)
+    }
 
-        let time_taken = SystemTime::now().duration_since(start_time).unwrap().as_millis();
-        self.add_run(time_taken);
+    fn check_time(&mut self) -> bool {
+        if !self.timed_out && self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            self.timed_out = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.timed_out
+    }
 
-        ranked_tiles
+    fn find_leaf_heuristic(&mut self, board: &OthelloBoard) -> f32 {
+        if !self.config.cache {
+            self.evals += 1;
+            return self.apply_contempt(board, eval::find_leaf_heuristic_with(board, self.config.eval_fn, self.config.scoring));
+        }
+        let hash_key = self.hasher.hash(board);
+        if let Some(heuristic) = self.eval_cache.get(hash_key) {
+            return self.apply_contempt(board, heuristic);
+        }
+        self.evals += 1;
+        let heuristic = eval::find_leaf_heuristic_with(board, self.config.eval_fn, self.config.scoring);
+        self.eval_cache.put(hash_key, heuristic);
+        self.apply_contempt(board, heuristic)
+    }
+
+    // scores an even endgame as the draw score of this search, which the nodes of the transposition table are keyed by
+    fn apply_contempt(&self, board: &OthelloBoard, heuristic: f32) -> f32 {
+        if heuristic == 0f32 && board.count_tiles(Color::Empty) <= eval::ENDGAME_EMPTY_COUNT {
+            self.draw_score
+        } else {
+            heuristic
+        }
     }
 
-    fn evaluate_base(&mut self, board: &OthelloBoard) -> f32 {
-        let mut heuristic = 0f32;
-        for depth_limit in 1..self.config.max_search_depth - 1 {
-            heuristic = self.evaluate(*board, depth_limit, board.black_move, f32::MIN, f32::MAX);
+    // the key a node is stored under in the transposition table, its score carries the draw score of the search
+    // that stored it, so a search from the other side or with another contempt never reads it
+    fn node_key(&self, board: &OthelloBoard) -> i64 {
+        let hash_key = self.hasher.hash(board);
+        if self.draw_score == 0f32 {
+            hash_key
+        } else {
+            hash_key ^ (self.draw_score.to_bits() as i64).wrapping_mul(DRAW_SCORE_KEY_SALT)
         }
+    }
+
+    // passed is set when the node was reached by the opponent passing
+    fn evaluate(&mut self, board: OthelloBoard, depth: u32, maximizer: bool, alpha: f32, beta: f32, passed: bool) -> f32 {
+        self.ply += 1;
+        debug_assert!(self.ply <= self.max_ply, "Search recursed {} plies deep, past the end of the game", self.ply);
+        let heuristic = self.evaluate_node(board, depth, maximizer, alpha, beta, passed);
+        self.ply -= 1;
         heuristic
     }
 
-    fn evaluate(&mut self, board: OthelloBoard, depth: u32, maximizer: bool, mut alpha: f32, mut beta: f32) -> f32 {
-        // stop when we reach depth floor
+    fn evaluate_node(
+        &mut self, board: OthelloBoard, depth: u32, maximizer: bool, mut alpha: f32, mut beta: f32, passed: bool
+    ) -> f32 {
+        self.nodes += 1;
+        // the value is thrown away by evaluate_base, so any will do
+        if self.check_time() {
+            return 0f32;
+        }
+        // stop when we reach depth floor, extending the search if the position is still tactical
         if depth == 0 {
-            return eval::find_heuristic(&board);
+            if self.config.quiescence {
+                return self.quiesce(board, MAX_QUIESCENCE_DEPTH, maximizer, alpha, beta);
+            }
+            return self.find_leaf_heuristic(&board);
         }
 
         // create then populate a vec of children for each move
         let mut children = SmallVec::<[OthelloBoard; 16]>::new();
-        board.find_current_moves(|mov| {
+        for mov in board.find_current_moves_smallvec() {
             // get the child board for the move and add it to children
             let child = board.make_move(mov);
             children.push(child);
-        });
+        }
 
         // cannot expand node's children
-        if children.len() == 0 {
-            return eval::find_heuristic(&board);
+        if children.is_empty() {
+            // the side to move must pass, so the opponent moves again on the same discs
+            // after a pass by each side in a row neither can move, so that is the end of the game whatever is left
+            if !passed && board.count_opponent_moves() > 0 {
+                return self.evaluate(board.make_pass(), depth - 1, !maximizer, alpha, beta, true);
+            }
+            return self.apply_contempt(&board, eval::find_terminal_score_with(&board, self.config.scoring));
         }
 
-        // check transposition table to see if we have a cache hit
-        let hash_key = self.hasher.hash(&board);
-        if let Some(node) = self.cache.get(hash_key) {
+        // check transposition table to see if we have a cache hit, the board is not even hashed with the cache off
+        let hash_key = self.config.cache.then(|| self.node_key(&board));
+        if let Some(node) = hash_key.and_then(|hash_key| self.cache.get(hash_key)) {
             if node.depth >= depth {
-                return node.heuristic;
+                // an exact score is the answer, while a bound can only narrow the window unless it closes it entirely
+                match node.bound {
+                    Bound::Exact => return node.heuristic,
+                    Bound::Lower if self.narrow_window => alpha = alpha.max(node.heuristic),
+                    Bound::Upper if self.narrow_window => beta = beta.min(node.heuristic),
+                    Bound::Lower if node.heuristic >= beta => return node.heuristic,
+                    Bound::Upper if node.heuristic <= alpha => return node.heuristic,
+                    _ => {}
+                }
+                if alpha >= beta {
+                    return node.heuristic;
+                }
             }
         }
+        // the window the children are searched with, to tell whether the result is exact or only a bound
+        let (alpha_start, beta_start) = (alpha, beta);
 
         if maximizer {
             // explore best children first for move ordering, find the best moves and return them
             for child in children {
-                alpha = alpha.max(self.evaluate(child, depth - 1, false, alpha, beta));
+                alpha = alpha.max(self.evaluate(child, depth - 1, false, alpha, beta, false));
                 // prune this branch, it cannot possibly be better than any child found so far
                 if alpha >= beta {
                     break;
                 }
             }
-            let node = CacheNode::new(hash_key, alpha, depth);
-            self.cache.put(node);
+            // a node cut short by the time limit has no real heuristic, so it must not be cached
+            if let Some(hash_key) = hash_key.filter(|_| !self.timed_out) {
+                let bound = Self::find_bound(alpha, alpha_start, beta_start);
+                self.cache.put(CacheNode::new(hash_key, alpha, depth, bound));
+            }
             alpha
         } else {
             // explore best children first for move ordering, find the best moves and return them
             for child in children {
-                beta = beta.min(self.evaluate(child, depth - 1, true, alpha, beta));
+                beta = beta.min(self.evaluate(child, depth - 1, true, alpha, beta, false));
                 // prune this branch, it cannot possibly be better than any child found so far
                 if beta <= alpha {
                     break;
                 }
             }
-            let node = CacheNode::new(hash_key, beta, depth);
-            self.cache.put(node);
+            if let Some(hash_key) = hash_key.filter(|_| !self.timed_out) {
+                let bound = Self::find_bound(beta, alpha_start, beta_start);
+                self.cache.put(CacheNode::new(hash_key, beta, depth, bound));
+            }
             beta
         }
     }
-}
\ No newline at end of file
+
+    // scores are clamped to the window, so one on an edge only proves the true score is at least or at most that edge
+    fn find_bound(heuristic: f32, alpha: f32, beta: f32) -> Bound {
+        if heuristic <= alpha {
+            Bound::Upper
+        } else if heuristic >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        }
+    }
+
+    // the node itself is counted by whoever visits it, evaluate_node for the root and the loops below for the rest
+    fn quiesce(&mut self, board: OthelloBoard, depth: u32, maximizer: bool, mut alpha: f32, mut beta: f32) -> f32 {
+        // the side to move can always decline a tactical move, so the static score is a bound for it
+        let stand_pat = self.find_leaf_heuristic(&board);
+        if depth == 0 {
+            return stand_pat;
+        }
+
+        // a position is "loud" while the side to move can immediately take a corner, or has so many more moves than
+        // the opponent that it is close to shutting the opponent out, in which case every move is followed
+        let moves = board.find_current_moves_smallvec();
+        let squeeze = moves.len() >= LOUD_MOBILITY_RATIO * board.count_opponent_moves().max(1);
+        let children = moves.into_iter()
+            .filter(|mov| squeeze || mov.is_corner())
+            .map(|mov| board.make_move(mov))
+            .collect::<SmallVec<[OthelloBoard; 16]>>();
+
+        // the position is quiet so the static score can be trusted
+        if children.is_empty() {
+            return stand_pat;
+        }
+
+        if maximizer {
+            alpha = alpha.max(stand_pat);
+            for child in children {
+                if alpha >= beta {
+                    break;
+                }
+                self.nodes += 1;
+                alpha = alpha.max(self.quiesce(child, depth - 1, false, alpha, beta));
+            }
+            alpha
+        } else {
+            beta = beta.min(stand_pat);
+            for child in children {
+                if beta <= alpha {
+                    break;
+                }
+                self.nodes += 1;
+                beta = beta.min(self.quiesce(child, depth - 1, true, alpha, beta));
+            }
+            beta
+        }
+    }
+}
+
+// shares one agent, and so one transposition table, between threads such as the handlers of a server
+// a search reads and writes the table at every node, so searches take turns holding the agent rather than running at
+// once, while positions one search stores are still there for the next no matter which thread runs it
+pub struct SyncAgent {
+    agent: Mutex<OthelloAgent>,
+}
+
+impl SyncAgent {
+    pub fn new(agent: OthelloAgent) -> Self {
+        Self { agent: Mutex::new(agent) }
+    }
+
+    pub fn find_best_move(&self, board: &OthelloBoard) -> Option<RankedTile> {
+        self.lock().find_best_move(board)
+    }
+
+    pub fn search(&self, board: &OthelloBoard, limits: SearchLimits) -> SearchResult {
+        self.lock().search(board, limits)
+    }
+
+    // a search that panicked leaves nothing half written that a later search can't recover from, since an
+    // unfinished node is never stored and every search starts its counts and ply from scratch, so the agent stays
+    // usable rather than poisoning every later request
+    fn lock(&self) -> MutexGuard<'_, OthelloAgent> {
+        self.agent.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::agent::{AgentConfig, OthelloAgent, SearchLimits, SyncAgent, Verdict, MAX_EXPLORE_DEPTH, VERDICT_THRESHOLD};
+    use crate::board::{Color, OthelloBoard};
+    use crate::eval;
+    use crate::eval::ScoringMode;
+    use crate::hasher::ZHasher;
+    use crate::tile::{Tile, TILES};
+
+    fn midgame_board(plies: usize) -> OthelloBoard {
+        // play a fixed line out of the opening to reach a branchy position
+        let mut board = OthelloBoard::new();
+        for i in 0..plies {
+            let moves = board.find_current_moves_as_vec();
+            board = board.make_move(moves[(i * 2) % moves.len()]);
+        }
+        board
+    }
+
+    fn count_nodes(board: &OthelloBoard, children: &[(Tile, OthelloBoard)]) -> (f32, u64) {
+        let mut agent = OthelloAgent::new(AgentConfig::new(6));
+        let best = agent.search_children(board, children).unwrap();
+        (best.heuristic, agent.last_nodes())
+    }
+
+    #[test]
+    fn test_bound_narrowing_searches_fewer_nodes() {
+        let mut narrowed_nodes = 0;
+        let mut shortcut_nodes = 0;
+        for plies in [12, 14, 16, 18] {
+            let board = midgame_board(plies);
+            // a null window search around the static heuristic leaves only bounds in the table, which a later full
+            // window search of the same position can narrow with but never shortcut on
+            let guess = eval::find_heuristic(&board);
+            let search = |narrow_window: bool| {
+                let mut agent = OthelloAgent::with_hasher(AgentConfig::new(6), ZHasher::from_seed(1));
+                agent.narrow_window = narrow_window;
+                agent.evaluate(board, 4, board.black_move, guess - 1f32, guess, false);
+                agent.nodes = 0;
+                let heuristic = agent.evaluate(board, 4, board.black_move, eval::LOSS, eval::WIN, false);
+                (heuristic, agent.nodes)
+            };
+            let (narrowed_heuristic, nodes) = search(true);
+            narrowed_nodes += nodes;
+            let (shortcut_heuristic, nodes) = search(false);
+            shortcut_nodes += nodes;
+
+            // narrowing only skips work that could not have changed the result
+            assert_eq!(narrowed_heuristic, shortcut_heuristic);
+        }
+        assert!(narrowed_nodes < shortcut_nodes, "narrowed {} nodes, shortcut only {}", narrowed_nodes, shortcut_nodes);
+    }
+
+    #[test]
+    fn test_root_ordering_searches_fewer_nodes() {
+        let mut ordered_nodes = 0;
+        let mut unordered_nodes = 0;
+        for plies in [12, 14, 16, 18] {
+            let board = midgame_board(plies);
+            let ordered = OthelloAgent::order_children(&board, eval::find_heuristic);
+            // the scan order the moves are generated in
+            let mut unordered = vec![];
+            for mov in board.find_current_moves_as_vec() {
+                let child = (mov, board.make_move(mov));
+                if !unordered.contains(&child) {
+                    unordered.push(child);
+                }
+            }
+
+            let (ordered_heuristic, nodes) = count_nodes(&board, &ordered);
+            ordered_nodes += nodes;
+            let (unordered_heuristic, nodes) = count_nodes(&board, &unordered);
+            unordered_nodes += nodes;
+
+            assert_eq!(ordered_heuristic, unordered_heuristic);
+        }
+
+        assert!(ordered_nodes < unordered_nodes);
+    }
+
+    #[test]
+    fn test_best_move_tie_break() {
+        // every opening move is symmetric to the others, so they all share the same heuristic
+        let board = OthelloBoard::new();
+        let mut agent = OthelloAgent::new(AgentConfig::new(4));
+        let best = agent.find_best_move(&board).unwrap();
+
+        assert_eq!(best.tile.to_string(), "d3");
+        assert_eq!(best.depth, 2);
+
+        let ranked = agent.find_ranked_moves(&board);
+        assert!(ranked.iter().all(|r| r.heuristic == best.heuristic && r.depth == best.depth));
+        // ranking breaks the ties the same way, so its best move is the one the best move search picks
+        let ranked_best = agent.search(&board, SearchLimits::ranked()).best.unwrap();
+        assert!(ranked_best.tile == best.tile);
+
+        // a static search ties every opening move too, and both searches break ties by the lowest tile index
+        let mut agent = OthelloAgent::new(AgentConfig::new(2));
+        let best = agent.find_best_move(&board).unwrap();
+        assert!(agent.search(&board, SearchLimits::ranked()).best.unwrap().tile == best.tile);
+    }
+
+    #[test]
+    fn test_search_result() {
+        let board = midgame_board(12);
+        let mut agent = OthelloAgent::new(AgentConfig::new(4));
+
+        let result = agent.search(&board, SearchLimits::best());
+        let best = result.best.unwrap();
+        assert!(result.ranked.is_empty());
+        assert_eq!(result.stats.depth, best.depth);
+        assert!(result.stats.hits + result.stats.misses > 0);
+        assert!(result.stats.nodes > 0 && result.stats.nodes == agent.last_nodes());
+        assert!(result.stats.evals > 0 && result.stats.evals < result.stats.nodes);
+
+        // a leaf reached again is answered by the eval cache instead of being evaluated twice
+        let evals = agent.evals;
+        let heuristic = agent.find_leaf_heuristic(&board);
+        assert_eq!(agent.find_leaf_heuristic(&board), heuristic);
+        assert_eq!(agent.evals, evals + 1);
+
+        // the best move for black is ranked last, and searching every move with a full window agrees on its heuristic
+        let result = agent.search(&board, SearchLimits::ranked());
+        let ranked_best = result.best.unwrap();
+        assert!(result.ranked.last().unwrap().tile == ranked_best.tile);
+        assert_eq!(ranked_best.heuristic, best.heuristic);
+    }
+
+    #[test]
+    fn test_cache_off() {
+        let board = midgame_board(12);
+        let cached = OthelloAgent::new(AgentConfig::new(5)).search(&board, SearchLimits::ranked());
+        let mut agent = OthelloAgent::new(AgentConfig::new(5).with_cache(false));
+        let uncached = agent.search(&board, SearchLimits::ranked());
+
+        // the table is never consulted, so the search has to visit more nodes to reach the same heuristics
+        assert_eq!(uncached.stats.hits + uncached.stats.misses, 0);
+        assert!(uncached.stats.nodes > cached.stats.nodes);
+        assert!(uncached.ranked.iter().zip(cached.ranked.iter()).all(|(a, b)| a.heuristic == b.heuristic));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let board = midgame_board(12);
+        let time_limit = Duration::from_millis(50);
+        let mut agent = OthelloAgent::new(AgentConfig::new(40).with_time_limit(Some(time_limit)));
+
+        let start_time = Instant::now();
+        let result = agent.search(&board, SearchLimits::best());
+
+        // a depth 40 search could never finish, so it must stop shortly after the limit with a partial result
+        assert!(result.stats.timed_out);
+        assert!(start_time.elapsed() < time_limit * 20);
+        let best = result.best.unwrap();
+        assert!(best.depth > 0 && best.depth < 38);
+        assert!(board.find_current_moves_smallvec().contains(&best.tile));
+
+        // the next search gets a fresh deadline
+        agent.set_config(AgentConfig::new(4).with_time_limit(Some(time_limit)));
+        assert!(!agent.search(&board, SearchLimits::best()).stats.timed_out);
+    }
+
+    #[test]
+    fn test_warm() {
+        let board = midgame_board(14);
+        let cold = OthelloAgent::with_hasher(AgentConfig::new(6), ZHasher::from_seed(1))
+            .search(&board, SearchLimits::best());
+
+        let mut agent = OthelloAgent::with_hasher(AgentConfig::new(6), ZHasher::from_seed(1));
+        let warm_stats = agent.warm(&board, 4);
+        assert!(warm_stats.depth < cold.stats.depth);
+        let warm = agent.search(&board, SearchLimits::best());
+
+        // the warmed table answers lookups the cold search had to expand, without changing the result
+        assert!(warm.stats.hits > cold.stats.hits);
+        assert!(warm.stats.nodes < cold.stats.nodes);
+        assert_eq!(warm.best.unwrap().heuristic, cold.best.unwrap().heuristic);
+        assert_eq!(warm.stats.depth, cold.stats.depth);
+    }
+
+    #[test]
+    fn test_verdict() {
+        assert_eq!(Verdict::from_score(eval::WIN, true), Verdict::Winning);
+        assert_eq!(Verdict::from_score(eval::WIN, false), Verdict::Losing);
+        assert_eq!(Verdict::from_score(eval::LOSS, true), Verdict::Losing);
+        assert_eq!(Verdict::from_score(eval::LOSS, false), Verdict::Winning);
+        assert_eq!(Verdict::from_score(VERDICT_THRESHOLD, true), Verdict::Winning);
+        assert_eq!(Verdict::from_score(0f32, true), Verdict::Unclear);
+        assert_eq!(Verdict::from_score(VERDICT_THRESHOLD - 1f32, false), Verdict::Unclear);
+
+        let mut agent = OthelloAgent::new(AgentConfig::new(4));
+        assert_eq!(agent.evaluation_verdict(&OthelloBoard::new()), Verdict::Unclear);
+
+        // a finished game wiped out for white is judged without searching
+        let mut board = OthelloBoard::new();
+        for tile in TILES {
+            board.set_tile(tile, Color::Black);
+        }
+        assert_eq!(agent.evaluation_verdict(&board), Verdict::Winning);
+        assert_eq!(agent.evaluation_verdict(&board.make_pass()), Verdict::Losing);
+    }
+
+    #[test]
+    fn test_sync_agent() {
+        let boards = [12, 13, 14, 15].map(midgame_board);
+        let expected = boards.map(|board| OthelloAgent::new(AgentConfig::new(4)).find_best_move(&board).unwrap().tile);
+
+        let agent = Arc::new(SyncAgent::new(OthelloAgent::new(AgentConfig::new(4))));
+        let handles = (0..8).map(|i| {
+            let agent = Arc::clone(&agent);
+            thread::spawn(move || {
+                let board = boards[i % boards.len()];
+                (i % boards.len(), agent.find_best_move(&board).unwrap().tile)
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            let (i, tile) = handle.join().unwrap();
+            assert!(tile == expected[i]);
+        }
+        assert!(agent.search(&boards[0], SearchLimits::ranked()).ranked.len() > 1);
+    }
+
+    // panics at the leaves two plies below a midgame root, so the search unwinds from inside the tree
+    fn panicking_eval(board: &OthelloBoard) -> f32 {
+        if board.count_tiles(Color::Empty) <= 46 {
+            panic!("deliberate panic");
+        }
+        eval::find_heuristic(board)
+    }
+
+    #[test]
+    fn test_sync_agent_after_panic() {
+        let board = midgame_board(12);
+        let config = AgentConfig::new(4);
+        let agent = Arc::new(SyncAgent::new(OthelloAgent::new(config.with_eval_fn(panicking_eval))));
+        let panicked = {
+            let agent = Arc::clone(&agent);
+            thread::spawn(move || agent.find_best_move(&board)).join()
+        };
+        assert!(panicked.is_err());
+        assert!(agent.lock().ply > 0);
+
+        agent.lock().set_config(config);
+        let expected = OthelloAgent::new(config).find_best_move(&board).unwrap();
+        assert!(agent.find_best_move(&board).unwrap().tile == expected.tile);
+        assert_eq!(agent.lock().ply, 0);
+    }
+
+    #[test]
+    fn test_explore() {
+        let board = midgame_board(13);
+        // a level of 2 has no deepening iterations, so each ranked move is scored by its child's static heuristic
+        let mut agent = OthelloAgent::new(AgentConfig::new(2));
+        let ranked = agent.find_ranked_moves(&board);
+        let tree = agent.explore(&board, 1).unwrap();
+
+        assert!(tree.mov.is_none() && tree.board == board);
+        assert_eq!(tree.children.len(), ranked.len());
+        for r in &ranked {
+            let node = tree.children.iter().find(|node| node.mov == Some(r.tile)).unwrap();
+            assert_eq!(node.score, r.heuristic);
+            assert!(node.board == board.make_move(r.tile) && node.children.is_empty());
+        }
+        assert_eq!(tree.score, ranked.last().unwrap().heuristic);
+
+        // deeper trees back their scores up by minimax from every grandchild
+        let tree = agent.explore(&board, 2).unwrap();
+        for child in &tree.children {
+            assert!(!child.children.is_empty());
+            let scores = child.children.iter().map(|node| node.score);
+            let best = if child.board.black_move { scores.fold(f32::MIN, f32::max) } else { scores.fold(f32::MAX, f32::min) };
+            assert_eq!(child.score, best);
+        }
+
+        assert!(agent.explore(&board, 0).unwrap().children.is_empty());
+        assert!(agent.explore(&board, MAX_EXPLORE_DEPTH + 1).is_err());
+    }
+
+    #[test]
+    fn test_deepening_schedule() {
+        let depths = |config: AgentConfig| config.deepening_depths().collect::<Vec<u32>>();
+        assert_eq!(depths(AgentConfig::new(7)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(depths(AgentConfig::new(7).with_start_depth(3)), vec![3, 4, 5]);
+        assert_eq!(depths(AgentConfig::new(7).with_step(2)), vec![1, 3, 5]);
+        assert_eq!(depths(AgentConfig::new(8).with_step(3)), vec![1, 4, 6]);
+        assert_eq!(depths(AgentConfig::new(7).with_start_depth(9)), vec![5]);
+        assert!(depths(AgentConfig::new(2)).is_empty());
+
+        // skipping shallow iterations only changes move ordering, not the move the full depth settles on
+        let board = midgame_board(13);
+        let best = OthelloAgent::new(AgentConfig::new(6)).find_best_move(&board).unwrap();
+        let config = AgentConfig::new(6).with_start_depth(2).with_step(2);
+        let coarse = OthelloAgent::new(config).find_best_move(&board).unwrap();
+        assert!(coarse.tile == best.tile);
+        assert_eq!(coarse.depth, best.depth);
+    }
+
+    #[test]
+    fn test_temperature_sampling() {
+        // a position whose best move is not tied with another, so the best move is unique
+        let board = midgame_board(13);
+        let best = OthelloAgent::new(AgentConfig::new(4)).find_best_move(&board).unwrap();
+
+        // a tiny temperature is effectively the same as always taking the best move
+        let mut agent = OthelloAgent::new(AgentConfig::new(4).with_temperature(1e-3));
+        for _ in 0..5 {
+            assert!(agent.find_best_move(&board).unwrap().tile == best.tile);
+        }
+
+        // a huge temperature samples close to uniformly, but identically for agents with the same seed
+        let mut agent = OthelloAgent::new(AgentConfig::new(4).with_temperature(1e6));
+        let mut other_agent = OthelloAgent::new(AgentConfig::new(4).with_temperature(1e6));
+        let mut sampled = vec![];
+        for _ in 0..20 {
+            let tile = agent.find_best_move(&board).unwrap().tile;
+            assert!(other_agent.find_best_move(&board).unwrap().tile == tile);
+            if !sampled.contains(&tile) {
+                sampled.push(tile);
+            }
+        }
+        assert!(sampled.len() > 1);
+    }
+
+    #[test]
+    fn test_endgame_prefers_more_flips() {
+        // a full board of white discs where black can take the a1 corner flipping one disc or play c8 flipping eight
+        let mut board = OthelloBoard::new();
+        for tile in TILES {
+            board.set_tile(tile, Color::White);
+        }
+        board.set_tile(Tile::new(0, 0), Color::Empty);
+        board.set_tile(Tile::new(0, 2), Color::Black);
+        board.set_tile(Tile::new(7, 2), Color::Empty);
+        board.set_tile(Tile::new(7, 6), Color::Black);
+
+        let mut agent = OthelloAgent::new(AgentConfig::new(3));
+        let best = agent.find_best_move(&board).unwrap();
+
+        assert_eq!(best.tile.to_string(), "c8");
+        assert_eq!(best.heuristic, -39f32);
+    }
+
+    #[test]
+    fn test_single_move_skips_search() {
+        // black's only move is a1, flanking the white disc on b1
+        let board = OthelloBoard::from_notation("EW6B/8B/8B/8B/8B/8B/8B/8B/B").unwrap();
+
+        let mut agent = OthelloAgent::new(AgentConfig::new(12));
+        let result = agent.search(&board, SearchLimits::best());
+        assert_eq!(result.best.unwrap().tile.to_string(), "a1");
+        assert_eq!(result.stats.nodes, 0);
+        assert_eq!(result.stats.depth, 0);
+    }
+
+    #[test]
+    fn test_proven_win_stops_deepening() {
+        // black can play a1 or h8, either starts a forced sweep of the board that white has no reply to
+        let board = OthelloBoard::from_notation("EW6B/8B/8B/8B/8B/8B/7BW/7BE/B").unwrap();
+        let mut agent = OthelloAgent::new(AgentConfig::new(12));
+        let best = agent.find_best_move(&board).unwrap();
+
+        assert!(eval::is_decisive_score(best.heuristic) && best.heuristic > 0f32);
+        assert!(best.depth < 12 - 2);
+    }
+
+    #[test]
+    fn test_contempt_avoids_draws() {
+        // white's best lines level the discs, and the best white can do after b8 is an even endgame
+        let board = OthelloBoard::from_notation("E7B/BEBWB2WE/2BWBWBWB/2B6W/2B3WB2W/EBW2BWBE/8B/W2EB4W/W").unwrap();
+        let even = OthelloAgent::new(AgentConfig::new(4)).find_best_move(&board).unwrap();
+        assert_eq!(even.heuristic, 0f32);
+
+        // with contempt the level line is worth less to white than a slightly worse one that keeps playing
+        let mut agent = OthelloAgent::new(AgentConfig::new(4).with_contempt(5f32));
+        let best = agent.find_best_move(&board).unwrap();
+        assert_ne!(best.tile.to_string(), "b8");
+        assert_eq!(best.heuristic, 2f32);
+    }
+
+    #[test]
+    fn test_contempt_kept_per_side_in_cache() {
+        // with 8 empty tiles the search reaches the end of every line, and white's best is a draw scored against white
+        let board = OthelloBoard::from_notation("WEW3BWE/2W4BWB/WBWB3WE/WB2W2BWB/W2B2WBWB/W7B/4W4B/2EBE2B2E/W").unwrap();
+        let config = AgentConfig::new(14).with_contempt(5f32);
+        assert_eq!(OthelloAgent::new(config).find_best_move(&board).unwrap().heuristic, 5f32);
+
+        // black searching the replies first stores its draws scored against black, which white must not read
+        let mut agent = OthelloAgent::new(config);
+        for mov in board.find_current_moves_smallvec() {
+            let child = board.make_move(mov);
+            if !child.must_pass() && !child.is_game_over() {
+                agent.find_best_move(&child);
+            }
+        }
+        assert_eq!(agent.find_best_move(&board).unwrap().heuristic, 5f32);
+    }
+
+    #[test]
+    fn test_eval_fn_override() {
+        // a stub that only cares whether black holds d3, which no white reply to d3 can flip
+        fn holds_d3(board: &OthelloBoard) -> f32 {
+            if board.get_tile(Tile::new(2, 3)) == Color::Black { 100f32 } else { 0f32 }
+        }
+        let mut agent = OthelloAgent::new(AgentConfig::new(3).with_eval_fn(holds_d3));
+        let best = agent.find_best_move(&OthelloBoard::new()).unwrap();
+        assert_eq!(best.tile.to_string(), "d3");
+        assert_eq!(best.heuristic, 100f32);
+
+        // searching a single reply deep, black never gets another move to reach d3 from the other openings
+        let ranked = agent.find_ranked_moves(&OthelloBoard::new());
+        assert!(ranked[..3].iter().all(|r| r.heuristic == 0f32));
+    }
+
+    #[test]
+    fn test_double_pass_is_terminal() {
+        // black has no moves but white does, normally black passes and white plays on
+        let board = OthelloBoard::from_notation("2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B").unwrap();
+        let mut agent = OthelloAgent::new(AgentConfig::new(6));
+
+        let played_on = agent.evaluate(board, 4, true, eval::LOSS, eval::WIN, false);
+        assert_ne!(played_on, eval::find_terminal_score(&board));
+
+        // but reached by white passing, black passing back would make two in a row so the game ends here
+        let ended = agent.evaluate(board, 4, true, eval::LOSS, eval::WIN, true);
+        assert_eq!(ended, eval::find_terminal_score(&board));
+        assert_eq!(agent.ply, 0);
+    }
+
+    #[test]
+    fn test_scoring_mode() {
+        // either of black's moves takes white's last disc, ending the game with 58 tiles left empty
+        let board = OthelloBoard::from_notation("8E/8E/8E/2E2BW3E/3E2B3E/8E/8E/8E/B").unwrap();
+        let wipeout = board.make_move(Tile::new(3, 5));
+        assert!(wipeout.is_game_over());
+
+        let strict = OthelloAgent::new(AgentConfig::new(4)).evaluate_position(&board);
+        assert_eq!(strict, eval::find_terminal_score(&wipeout));
+        let config = AgentConfig::new(4).with_scoring(ScoringMode::EmptiesToWinner);
+        let awarded = OthelloAgent::new(config).evaluate_position(&board);
+        assert_eq!(awarded, eval::WIN);
+        assert!(awarded > strict);
+    }
+
+    #[test]
+    fn test_forced_loss_still_moves() {
+        // black's only move flips one disc and ends the game with a loss
+        let mut board = OthelloBoard::new();
+        for tile in TILES {
+            board.set_tile(tile, Color::White);
+        }
+        board.set_tile(Tile::new(0, 0), Color::Empty);
+        board.set_tile(Tile::new(0, 2), Color::Black);
+
+        let mut agent = OthelloAgent::new(AgentConfig::new(4));
+        let best = agent.find_best_move(&board).unwrap();
+
+        assert_eq!(best.tile.to_string(), "a1");
+        assert_eq!(best.heuristic, eval::find_terminal_score(&board.make_move(best.tile)));
+        assert!(best.heuristic < eval::find_heuristic(&board) && best.heuristic > eval::LOSS);
+    }
+
+    #[test]
+    fn test_quiescence_smooths_evaluation() {
+        // black to move can immediately take the a1 corner by flanking a long run of white discs
+        let mut board = OthelloBoard::new();
+        for row in 1..5 {
+            board.set_tile(Tile::new(row, 0), Color::White);
+        }
+        board.set_tile(Tile::new(5, 0), Color::Black);
+        for tile in [Tile::new(6, 6), Tile::new(6, 1), Tile::new(1, 6)] {
+            board.set_tile(tile, Color::White);
+        }
+
+        let mut plain = OthelloAgent::new(AgentConfig::new(3));
+        let mut quiet = OthelloAgent::new(AgentConfig::new(3).with_quiescence(true));
+
+        let static_score = plain.evaluate(board, 0, true, eval::LOSS, eval::WIN, false);
+        let deeper_score = plain.evaluate(board, 1, true, eval::LOSS, eval::WIN, false);
+        let quiet_score = quiet.evaluate(board, 0, true, eval::LOSS, eval::WIN, false);
+
+        assert!(quiet_score > static_score);
+        assert!((quiet_score - deeper_score).abs() < (static_score - deeper_score).abs());
+    }
+
+    #[test]
+    fn test_quiescence_mobility_squeeze() {
+        // a quiet position is a single node, visited by evaluate_node and not counted again by the quiescence search
+        let mut agent = OthelloAgent::new(AgentConfig::new(3).with_quiescence(true));
+        agent.evaluate(OthelloBoard::new(), 0, true, eval::LOSS, eval::WIN, false);
+        assert_eq!(agent.nodes, 1);
+
+        // black's one disc is ringed by white, so black has 8 moves while white has none, with no corner among them
+        let mut board = OthelloBoard::empty();
+        for row in 2..5 {
+            for col in 2..5 {
+                board.set_tile(Tile::new(row, col), if (row, col) == (3, 3) { Color::Black } else { Color::White });
+            }
+        }
+        assert_eq!((board.count_current_moves(), board.count_opponent_moves()), (8, 0));
+        let mut agent = OthelloAgent::new(AgentConfig::new(3).with_quiescence(true));
+        agent.evaluate(board, 0, true, eval::LOSS, eval::WIN, false);
+        assert!(agent.nodes > 1);
+    }
+}
@@ -3,50 +3,219 @@
  */
 
 use std::fmt;
+use smallvec::SmallVec;
 use crate::errors::{ParseResult, ParseError};
-use crate::tile::{Tile, TILES};
+use crate::tile::{CoordStyle, Tile, TILES};
 
-pub const EMPTY: u8 = 0;
-pub const WHITE: u8 = 1;
-pub const BLACK: u8 = 2;
-const DIRECTIONS: [[i8; 2]; 8] = [[0, 1], [0, -1], [1, 0], [-1, 0], [-1, -1], [-1, 1], [1, -1], [1, 1]];
+// the notation symbol for each color, indexed by the color so every conversion shares one mapping
+const SYMBOLS: [char; 3] = ['E', 'W', 'B'];
+pub const DIRECTIONS: [[i8; 2]; 8] = [[0, 1], [0, -1], [1, 0], [-1, 0], [-1, -1], [-1, 1], [1, -1], [1, 1]];
+// for variants where discs can only be flanked along rows and columns
+pub const ORTHOGONAL_DIRECTIONS: [[i8; 2]; 4] = [[0, 1], [0, -1], [1, 0], [-1, 0]];
+// tiles on the first and last columns, a shift that steps a column sideways must drop whatever wrapped onto them
+const FIRST_COL: u64 = 0x0101_0101_0101_0101;
+const LAST_COL: u64 = 0x8080_8080_8080_8080;
+// a game fills the 60 tiles around the center one move at a time, and a side only passes when the other can move next,
+// so a game can't outlast a move for every tile with a pass before each
+pub const MAX_GAME_MOVES: usize = 60;
+pub const MAX_GAME_PLIES: usize = 2 * MAX_GAME_MOVES;
+const CENTER: [Tile; 4] = [Tile::new(3, 3), Tile::new(3, 4), Tile::new(4, 3), Tile::new(4, 4)];
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+// the lines a board can be reflected across, the diagonal runs from a1 to h8 and the antidiagonal from h1 to a8
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+// moves every tile in the mask one step in the direction, dropping any that step off the board
+fn shift_mask(mask: u64, direction: [i8; 2]) -> u64 {
+    let delta = direction[0] * 8 + direction[1];
+    let shifted = if delta > 0 { mask << delta } else { mask >> -delta };
+    match direction[1] {
+        1 => shifted & !FIRST_COL,
+        -1 => shifted & !LAST_COL,
+        _ => shifted,
+    }
+}
+
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Axis::Horizontal => "horizontal",
+            Axis::Vertical => "vertical",
+            Axis::Diagonal => "diagonal",
+            Axis::AntiDiagonal => "antidiagonal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// the eight symmetries of the board, each as the reflections to apply in order, starting with the identity
+pub const SYMMETRIES: [&[Axis]; 8] = [
+    &[],
+    &[Axis::Horizontal],
+    &[Axis::Vertical],
+    &[Axis::Diagonal],
+    &[Axis::AntiDiagonal],
+    &[Axis::Horizontal, Axis::Vertical],
+    &[Axis::Horizontal, Axis::Diagonal],
+    &[Axis::Diagonal, Axis::Horizontal],
+];
+
+// applies the reflections of a symmetry to a tile, undone by applying them again in reverse
+pub fn transform_tile(symmetry: &[Axis], tile: Tile) -> Tile {
+    symmetry.iter().fold(tile, |tile, axis| axis.reflect(tile))
+}
+
+pub fn untransform_tile(symmetry: &[Axis], tile: Tile) -> Tile {
+    symmetry.iter().rev().fold(tile, |tile, axis| axis.reflect(tile))
+}
+
+impl Axis {
+    pub fn reflect(self, tile: Tile) -> Tile {
+        match self {
+            Axis::Horizontal => Tile::new(7 - tile.row, tile.col),
+            Axis::Vertical => Tile::new(tile.row, 7 - tile.col),
+            Axis::Diagonal => Tile::new(tile.col, tile.row),
+            Axis::AntiDiagonal => Tile::new(7 - tile.col, 7 - tile.row),
+        }
+    }
+}
+
+// the value of each color is what is packed into the board, so the discriminants must not change
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
+    Empty = 0,
+    White = 1,
+    Black = 2,
+}
+
+impl Color {
+    pub const fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Color::White,
+            2 => Color::Black,
+            _ => Color::Empty,
+        }
+    }
+
+    pub const fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+            Color::Empty => Color::Empty,
+        }
+    }
+
+    pub fn to_symbol(self) -> char {
+        SYMBOLS[self as usize]
+    }
+
+    // symbols are read in either case, though they are always written in uppercase
+    pub fn from_symbol(sym: char) -> Option<Self> {
+        let sym = sym.to_ascii_uppercase();
+        SYMBOLS.iter().position(|s| *s == sym).map(|value| Self::from_u8(value as u8))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct OthelloBoard {
     board: i128,
     pub black_move: bool,
+    // disc counts kept up to date by every write to the board, so counting never scans the tiles
+    black_count: u8,
+    white_count: u8,
 }
 
 impl OthelloBoard {
     pub fn new() -> Self {
-        let mut board = Self { board: 0, black_move: true };
-        board.set_tile(Tile::new(3, 3), WHITE);
-        board.set_tile(Tile::new(3, 4), BLACK);
-        board.set_tile(Tile::new(4, 3), BLACK);
-        board.set_tile(Tile::new(4, 4), WHITE);
+        let mut board = Self::empty();
+        board.set_tile(Tile::new(3, 3), Color::White);
+        board.set_tile(Tile::new(3, 4), Color::Black);
+        board.set_tile(Tile::new(4, 3), Color::Black);
+        board.set_tile(Tile::new(4, 4), Color::White);
         board
     }
 
-    pub fn set_tile(&mut self, tile: Tile, color: u8) {
+    // a board without the starting discs, which are placed by the players under reversi rules
+    pub fn empty() -> Self {
+        Self { board: 0, black_move: true, black_count: 0, white_count: 0 }
+    }
+
+    pub fn set_tile(&mut self, tile: Tile, color: Color) {
+        match self.get_tile(tile) {
+            Color::Black => self.black_count -= 1,
+            Color::White => self.white_count -= 1,
+            Color::Empty => {}
+        }
+        match color {
+            Color::Black => self.black_count += 1,
+            Color::White => self.white_count += 1,
+            Color::Empty => {}
+        }
+        self.write_tile(tile, color);
+    }
+
+    // writes the tile without touching the disc counts, the caller must keep them up to date itself
+    fn write_tile(&mut self, tile: Tile, color: Color) {
         let p = (tile.row * 8 + tile.col) * 2;
         let clear_mask = !(1 << p) & !(1 << (p + 1));
         self.board &= clear_mask;
-        self.board |= (color as i128) << p;
+        self.board |= (color.to_u8() as i128) << p;
     }
 
-    pub fn get_tile(&self, tile: Tile) -> u8 {
+    pub fn get_tile(&self, tile: Tile) -> Color {
         let mask = (1 << 2) - 1;
         let p = (tile.row * 8 + tile.col) * 2;
-        (mask & (self.board >> p)) as u8
+        Color::from_u8((mask & (self.board >> p)) as u8)
+    }
+
+    // the tiles adjacent to the tile in any of the 8 directions, leaving out the ones off the board
+    pub fn neighbors(&self, tile: Tile) -> SmallVec<[Tile; 8]> {
+        DIRECTIONS.iter().filter_map(|direction| tile.offset(direction[0], direction[1])).collect()
+    }
+
+    pub fn has_empty_neighbor(&self, tile: Tile) -> bool {
+        self.neighbors(tile).into_iter().any(|neighbor| self.get_tile(neighbor) == Color::Empty)
+    }
+
+    pub fn current_color(&self) -> Color {
+        if self.black_move { Color::Black } else { Color::White }
+    }
+
+    // the tiles packed 2 bits each in index order, without the side to move
+    pub fn raw(&self) -> i128 {
+        self.board
     }
 
     pub fn find_current_moves(&self, on_move: impl FnMut(Tile)) {
-        let color = if self.black_move { BLACK } else { WHITE };
-        self.find_potential_moves(color, on_move)
+        self.find_potential_moves(self.current_color(), on_move)
     }
 
-    pub fn find_potential_moves(&self, color: u8, mut on_move: impl FnMut(Tile)) {
-        let opposite_color = if color == BLACK { WHITE } else { BLACK };
+    pub fn find_potential_moves(&self, color: Color, on_move: impl FnMut(Tile)) {
+        self.find_potential_moves_with(color, &DIRECTIONS, on_move)
+    }
+
+    // finds moves that flank along the given directions only, the standard game flanks along all of them
+    pub fn find_potential_moves_with(&self, color: Color, directions: &[[i8; 2]], mut on_move: impl FnMut(Tile)) {
+        // under reversi rules the first four discs are placed freely in the center without flanking
+        if CENTER.iter().any(|tile| self.get_tile(*tile) == Color::Empty) {
+            for tile in CENTER {
+                if self.get_tile(tile) == Color::Empty {
+                    on_move(tile);
+                }
+            }
+            return;
+        }
+
+        let opposite_color = color.opposite();
 
         // check each disc for potential flanks
         for disc in TILES.into_iter() {
@@ -55,81 +224,232 @@ impl OthelloBoard {
                 continue;
             }
             // check each direction from disc for potential flank
-            for direction in DIRECTIONS {
-                let mut tile = Tile::new(disc.row + direction[0], disc.col + direction[1]);
+            for direction in directions {
+                let mut next = disc.offset(direction[0], direction[1]);
 
                 // iterate from disc to next opposite color
                 let mut count = 0;
-                while tile.in_bounds() {
+                while let Some(tile) = next {
                     if self.get_tile(tile) != opposite_color {
                         break;
                     }
-                    tile.row += direction[0];
-                    tile.col += direction[1];
+                    next = tile.offset(direction[0], direction[1]);
                     count += 1;
                 }
                 // add move to potential moves list assuming
                 // we flank at least once disc, the tile is in bounds and is empty
-                if count > 0 && tile.in_bounds() && self.get_tile(tile) == EMPTY {
-                    // invoke move event
-                    on_move(tile);
+                if let Some(tile) = next {
+                    if count > 0 && self.get_tile(tile) == Color::Empty {
+                        // invoke move event
+                        on_move(tile);
+                    }
                 }
             }
         }
     }
 
     pub fn make_move(&self, mov: Tile) -> OthelloBoard {
+        self.make_move_with(mov, &DIRECTIONS)
+    }
+
+    // makes a move that only flips discs flanked along the given directions
+    pub fn make_move_with(&self, mov: Tile, directions: &[[i8; 2]]) -> OthelloBoard {
         // copies the current board to a new child board
         let mut board = *self;
+        board.apply_move_with(mov, directions);
+        board
+    }
 
-        let opposite_color = if board.black_move { WHITE } else { BLACK };
-        let current_color = if board.black_move { BLACK } else { WHITE };
+    // makes the move on this board rather than on a copy, for callers that own the board and don't need the parent
+    pub fn apply_move(&mut self, mov: Tile) {
+        self.apply_move_with(mov, &DIRECTIONS)
+    }
+
+    fn apply_move_with(&mut self, mov: Tile, directions: &[[i8; 2]]) {
+        let board = self;
+
+        let current_color = board.current_color();
+        let opposite_color = current_color.opposite();
 
         board.black_move = !board.black_move;
         board.set_tile(mov, current_color);
+        let mut flips = 0;
 
         // check each direction of new disc position
-        for direction in DIRECTIONS {
-            let initial_tile = Tile::new(mov.row + direction[0], mov.col + direction[1]);
-            let mut tile = Tile::new(initial_tile.row, initial_tile.col);
+        for direction in directions {
+            let mut next = mov.offset(direction[0], direction[1]);
 
             let mut flank = false;
 
             // iterate from disc until first potential flank
-            while tile.in_bounds() {
+            while let Some(tile) = next {
                 if board.get_tile(tile) == current_color {
                     flank = true;
                     break;
-                } else if board.get_tile(tile) == EMPTY {
+                } else if board.get_tile(tile) == Color::Empty {
                     break;
                 }
-                tile.row += direction[0];
-                tile.col += direction[1];
+                next = tile.offset(direction[0], direction[1]);
             }
 
             if !flank {
                 continue;
             }
 
-            tile.row = initial_tile.row;
-            tile.col = initial_tile.col;
-
             // flip each disc to opposite color to flank, update disc counts
-            while tile.in_bounds() {
+            let mut next = mov.offset(direction[0], direction[1]);
+            while let Some(tile) = next {
                 if board.get_tile(tile) != opposite_color {
                     break;
                 }
 
-                board.set_tile(tile, current_color);
+                board.write_tile(tile, current_color);
+                flips += 1;
+
+                next = tile.offset(direction[0], direction[1]);
+            }
+        }
+
+        // each flipped disc moves from the opponent's count to the mover's
+        if current_color == Color::Black {
+            board.black_count += flips;
+            board.white_count -= flips;
+        } else {
+            board.white_count += flips;
+            board.black_count -= flips;
+        }
+    }
+
+    // like make_move but returns none for an illegal move, such as one that flips no discs, instead of corrupting the board
+    pub fn checked_make_move(&self, mov: Tile) -> Option<OthelloBoard> {
+        if self.find_current_moves_smallvec().contains(&mov) {
+            Some(self.make_move(mov))
+        } else {
+            None
+        }
+    }
+
+    // parses a move like "d3" and applies it, failing if the move is not legal for the side to move
+    pub fn try_move(&self, notation: &str) -> ParseResult<OthelloBoard> {
+        self.try_move_in(notation, CoordStyle::Algebraic)
+    }
+
+    // like try_move but for a move written in any coordinate style
+    pub fn try_move_in(&self, notation: &str, style: CoordStyle) -> ParseResult<OthelloBoard> {
+        let mut board = *self;
+        board.try_apply_move_in(notation, style)?;
+        Ok(board)
+    }
+
+    // like try_move_in but makes the move on this board, leaving it untouched if the move can't be made
+    pub fn try_apply_move_in(&mut self, notation: &str, style: CoordStyle) -> ParseResult<()> {
+        let mov = style.parse(notation)?;
+        if !self.find_current_moves_smallvec().contains(&mov) {
+            return Err(ParseError::IllegalMove("Not a valid move".into()))
+        }
+        self.apply_move(mov);
+        Ok(())
+    }
 
-                tile.row += direction[0];
-                tile.col += direction[1];
+    // the discs the side to move would flip by playing the move, without playing it, or none if the move is not legal
+    pub fn find_flips(&self, mov: Tile) -> Option<Vec<Tile>> {
+        self.checked_make_move(mov)?;
+        let flipped = self.flipped_mask(mov, self.current_color());
+        Some(TILES.into_iter().filter(|tile| flipped & (1 << tile.index()) != 0).collect())
+    }
+
+    // the tiles holding discs of the color as a bitmask indexed by tile
+    pub fn discs_mask(&self, color: Color) -> u64 {
+        // each tile's pair of bits is 01 for white and 10 for black, so pick out the matching bit of every pair
+        const LOW_BITS: u128 = 0x5555_5555_5555_5555_5555_5555_5555_5555;
+        let bits = self.board as u128;
+        let mut x = match color {
+            Color::White => bits & LOW_BITS & !(bits >> 1),
+            Color::Black => (bits >> 1) & LOW_BITS & !bits,
+            Color::Empty => !(bits | (bits >> 1)) & LOW_BITS,
+        };
+        // squeeze out the gaps between the picked bits, halving the spacing each step until they are adjacent
+        x = (x | (x >> 1)) & 0x3333_3333_3333_3333_3333_3333_3333_3333;
+        x = (x | (x >> 2)) & 0x0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F;
+        x = (x | (x >> 4)) & 0x00FF_00FF_00FF_00FF_00FF_00FF_00FF_00FF;
+        x = (x | (x >> 8)) & 0x0000_FFFF_0000_FFFF_0000_FFFF_0000_FFFF;
+        x = (x | (x >> 16)) & 0x0000_0000_FFFF_FFFF_0000_0000_FFFF_FFFF;
+        x = (x | (x >> 32)) & 0x0000_0000_0000_0000_FFFF_FFFF_FFFF_FFFF;
+        x as u64
+    }
+
+    // the opponent discs the color would flip by playing the move, found by shifting whole bitboards one step at a time
+    // a mask of 0 means the move flips nothing, which is illegal except when placing the first discs into the center
+    pub fn flipped_mask(&self, mov: Tile, color: Color) -> u64 {
+        let own = self.discs_mask(color);
+        let opponent = self.discs_mask(color.opposite());
+        let mov_bit = 1u64 << mov.index();
+        if (own | opponent) & mov_bit != 0 {
+            return 0;
+        }
+
+        let mut flipped = 0u64;
+        for direction in DIRECTIONS {
+            // walk over the run of opponent discs in this direction, keeping it only if an own disc closes it off
+            let mut run = 0u64;
+            let mut cursor = shift_mask(mov_bit, direction);
+            while cursor & opponent != 0 {
+                run |= cursor;
+                cursor = shift_mask(cursor, direction);
             }
+            if cursor & own != 0 {
+                flipped |= run;
+            }
+        }
+        flipped
+    }
+
+    // counts the positions reached after exactly depth plies, a forced pass counts as a ply and a finished game
+    // counts as a single position no matter how much depth is left
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.find_current_moves_smallvec();
+        if moves.is_empty() {
+            return if self.count_opponent_moves() > 0 { self.make_pass().perft(depth - 1) } else { 1 };
         }
+        moves.into_iter().map(|mov| self.make_move(mov).perft(depth - 1)).sum()
+    }
+
+    // the same position with every disc and the turn given to the other side
+    pub fn color_swapped(&self) -> OthelloBoard {
+        // white and black are 01 and 10 in each tile's pair of bits, so swapping the bits of every pair swaps the colors
+        const LOW_BITS: i128 = 0x5555_5555_5555_5555_5555_5555_5555_5555;
+        let mut board = *self;
+        board.board = ((self.board & LOW_BITS) << 1) | ((self.board >> 1) & LOW_BITS);
+        board.black_move = !self.black_move;
+        board.black_count = self.white_count;
+        board.white_count = self.black_count;
+        board
+    }
 
+    // the same position reflected across the axis, the turn is unchanged
+    pub fn mirrored(&self, axis: Axis) -> OthelloBoard {
+        let mut board = *self;
+        for tile in TILES {
+            board.set_tile(axis.reflect(tile), self.get_tile(tile));
+        }
         board
     }
 
+    pub fn transformed(&self, symmetry: &[Axis]) -> OthelloBoard {
+        symmetry.iter().fold(*self, |board, axis| board.mirrored(*axis))
+    }
+
+    // the one board among the symmetries of this one that all of them share, along with the symmetry that reaches it
+    pub fn canonical(&self) -> (OthelloBoard, &'static [Axis]) {
+        SYMMETRIES.iter()
+            .map(|symmetry| (self.transformed(symmetry), *symmetry))
+            .min_by_key(|(board, _)| board.board)
+            .unwrap()
+    }
+
     pub fn find_current_moves_as_vec(&self) -> Vec<Tile> {
         let mut moves = vec![];
         self.find_current_moves(|mov| {
@@ -138,55 +458,127 @@ impl OthelloBoard {
         moves
     }
 
-    pub fn count_potential_moves(&self, color: u8) -> usize {
-        let mut count = 0;
-        self.find_potential_moves(color, |_| count += 1);
-        count
+    // collects each legal move once without allocating, for use on the hot search path
+    pub fn find_current_moves_smallvec(&self) -> SmallVec<[Tile; 16]> {
+        self.find_potential_moves_smallvec(self.current_color())
     }
 
-    pub fn get_symbol(&self, tile: Tile) -> char {
-        match self.get_tile(tile) {
-            1 => 'B',
-            2 => 'W',
-            _ => 'E'
+    pub fn find_potential_moves_smallvec(&self, color: Color) -> SmallVec<[Tile; 16]> {
+        let mut moves = SmallVec::new();
+        self.find_potential_moves(color, |mov| {
+            if !moves.contains(&mov) {
+                moves.push(mov)
+            }
+        });
+        moves
+    }
+
+    // the moves for the color as a bitmask indexed by tile, so checking a move is a single bit test
+    pub fn find_potential_moves_mask(&self, color: Color) -> u64 {
+        // a move can be found from multiple flanking discs, but a mask holds each once
+        let mut moves_mask = 0u64;
+        self.find_potential_moves(color, |mov| moves_mask |= 1 << mov.index());
+        moves_mask
+    }
+
+    pub fn find_current_moves_mask(&self) -> u64 {
+        self.find_potential_moves_mask(self.current_color())
+    }
+
+    pub fn count_potential_moves(&self, color: Color) -> usize {
+        self.find_potential_moves_mask(color).count_ones() as usize
+    }
+
+    pub fn count_tiles(&self, color: Color) -> usize {
+        match color {
+            Color::Black => self.black_count as usize,
+            Color::White => self.white_count as usize,
+            Color::Empty => 64 - self.black_count as usize - self.white_count as usize,
         }
     }
 
+    // how many moves have been played since the start position, read off the discs since passes leave nothing behind
+    // the discs placed into an empty center are not counted, so any board is at most MAX_GAME_MOVES in
+    pub fn moves_played(&self) -> usize {
+        MAX_GAME_MOVES - self.count_tiles(Color::Empty).min(MAX_GAME_MOVES)
+    }
+
+    // the black and white disc counts
+    pub fn disc_counts(&self) -> (usize, usize) {
+        (self.black_count as usize, self.white_count as usize)
+    }
+
+    pub fn count_current_moves(&self) -> usize {
+        self.count_potential_moves(self.current_color())
+    }
+
+    pub fn count_opponent_moves(&self) -> usize {
+        self.count_potential_moves(self.current_color().opposite())
+    }
+
+    // the side to move has no legal moves but the opponent does, so the turn goes back to the opponent
+    pub fn must_pass(&self) -> bool {
+        self.count_current_moves() == 0 && self.count_opponent_moves() > 0
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.count_current_moves() == 0 && self.count_opponent_moves() == 0
+    }
+
+    pub fn make_pass(&self) -> OthelloBoard {
+        let mut board = *self;
+        board.black_move = !board.black_move;
+        board
+    }
+
+    pub fn get_symbol(&self, tile: Tile) -> char {
+        self.get_tile(tile).to_symbol()
+    }
+
     pub fn set_symbol(&mut self, tile: Tile, sym: char) -> ParseResult<()> {
-        let byte = match sym {
-            'E' => 0u8,
-            'B' => 1u8,
-            'W' => 2u8,
-            _ => {
-                return Err(ParseError::new("Tile symbol must be E, B or W"))
+        let color = match Color::from_symbol(sym) {
+            Some(color) => color,
+            None => {
+                return Err(ParseError::InvalidNotation("Tile symbol must be E, B or W".into()))
             }
         };
-        self.set_tile(tile, byte);
+        self.set_tile(tile, color);
         Ok(())
     }
 
+    pub fn get_turn(&self) -> char {
+        self.current_color().to_symbol()
+    }
+
     pub fn set_turn(&mut self, sym: char) -> ParseResult<()> {
-        self.black_move = match sym {
-            'B' => true,
-            'W' => false,
+        self.black_move = match Color::from_symbol(sym) {
+            Some(Color::Black) => true,
+            Some(Color::White) => false,
             _ => {
-                return Err(ParseError::new("Turn must be B or W"))
+                return Err(ParseError::InvalidNotation("Turn must be B or W".into()))
             }
         };
         Ok(())
     }
 
     pub fn from_notation(str: &str) -> ParseResult<Self> {
-        let mut board = OthelloBoard::new();
+        let mut board = OthelloBoard::empty();
         let mut row = 0;
         let mut col = 0;
         let mut count = 1;
-        for c in str.chars() {
+        for (i, c) in str.char_indices() {
             if row > 7 {
+                // everything after the last row must be the single turn symbol
+                if str[i..].chars().count() > 1 {
+                    return Err(ParseError::InvalidNotation("Cannot have more than 8 rows".into()))
+                }
                 board.set_turn(c)?;
-                break;
+                return Ok(board)
             }
             if c == '/' {
+                if col != 8 {
+                    return Err(ParseError::InvalidNotation("Must have exactly 8 cols per row".into()))
+                }
                 // slash means we need to go to the next row
                 row += 1;
                 col = 0;
@@ -197,7 +589,7 @@ impl OthelloBoard {
                     // otherwise output the current sym, count number of times and reset the count
                     None => {
                         if count + col > 8 {
-                            return Err(ParseError::new("Cannot have more than 8 cols per row"))
+                            return Err(ParseError::InvalidNotation("Cannot have more than 8 cols per row".into()))
                         }
                         // write the counted number of symbols and go to the next col
                         for _ in 0..count {
@@ -209,10 +601,14 @@ impl OthelloBoard {
                 }
             }
         }
-        Ok(board)
+        if row > 7 {
+            Err(ParseError::InvalidNotation("Must end with the turn symbol".into()))
+        } else {
+            Err(ParseError::InvalidNotation("Must have exactly 8 rows".into()))
+        }
     }
 
-    pub fn to_notation(&self) -> String {
+    pub fn to_notation(self) -> String {
         let mut tiles_str = String::with_capacity(66);
         let mut count = 0;
         let mut sym = self.get_symbol(Tile::from_index(0));
@@ -250,11 +646,25 @@ impl OthelloBoard {
                 tiles_str.push('/');
             }
         }
-        tiles_str.push(if self.black_move { 'B' } else { 'W' });
+        tiles_str.push(self.get_turn());
         tiles_str
     }
 }
 
+impl Default for OthelloBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<&str> for OthelloBoard {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> ParseResult<Self> {
+        Self::from_notation(str)
+    }
+}
+
 impl fmt::Display for OthelloBoard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut board_str = String::from("");
@@ -262,16 +672,30 @@ impl fmt::Display for OthelloBoard {
         board_str.push_str("  ");
         // add each column header as letter
         for i in 0u8..8 {
-            board_str.push((('a' as u8) + i) as char);
+            board_str.push((b'a' + i) as char);
             board_str.push(' ');
         }
         board_str.push('\n');
+        // the alternate format marks legal moves for the side to move
+        let moves = if f.alternate() { self.find_current_moves_as_vec() } else { vec![] };
         // add each matrix element in board with row header
         for row in 0..8 {
             board_str.push_str(&(row + 1).to_string());
             board_str.push(' ');
             for col in 0..8 {
-                board_str.push_str(&self.get_tile(Tile::new(row, col)).to_string());
+                let tile = Tile::new(row, col);
+                if f.alternate() {
+                    // render discs as unicode symbols so the board is readable on a terminal
+                    let sym = match self.get_tile(tile) {
+                        Color::Black => '●',
+                        Color::White => '○',
+                        Color::Empty if moves.contains(&tile) => '*',
+                        Color::Empty => '·'
+                    };
+                    board_str.push(sym);
+                } else {
+                    board_str.push_str(&self.get_tile(tile).to_u8().to_string());
+                }
                 board_str.push(' ');
             }
             board_str.push('\n');
@@ -282,25 +706,572 @@ impl fmt::Display for OthelloBoard {
 
 #[cfg(test)]
 mod tests {
-    use crate::board::OthelloBoard;
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::board::{
+        transform_tile, untransform_tile, Axis, Color, OthelloBoard, CENTER, MAX_GAME_MOVES, MAX_GAME_PLIES,
+        ORTHOGONAL_DIRECTIONS, SYMMETRIES
+    };
+    use crate::errors::ParseError;
+    use crate::tile::{CoordStyle, Tile, TILES};
+
+    proptest! {
+        #[test]
+        fn test_from_notation_never_panics(str in "\\PC*") {
+            let _ = OthelloBoard::from_notation(&str);
+        }
+
+        // arbitrary strings rarely look like notation, so also fuzz with strings made of notation symbols
+        #[test]
+        fn test_from_notation_symbols_never_panics(str in "[0-9EBW/]{0,80}") {
+            let _ = OthelloBoard::from_notation(&str);
+        }
+
+        #[test]
+        fn test_notation_round_trip(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let board = random_walk(OthelloBoard::new(), &choices);
+            let other_board = OthelloBoard::from_notation(&board.to_notation()).unwrap();
+            prop_assert_eq!(board, other_board);
+
+            let lower_board = OthelloBoard::from_notation(&board.to_notation().to_lowercase()).unwrap();
+            prop_assert_eq!(board, lower_board);
+        }
+
+        #[test]
+        fn test_color_swap_round_trip(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let board = random_walk(OthelloBoard::new(), &choices);
+            let swapped = board.color_swapped();
+            prop_assert_eq!(swapped.color_swapped(), board);
+            for tile in TILES {
+                prop_assert_eq!(swapped.get_tile(tile), board.get_tile(tile).opposite());
+            }
+            prop_assert_eq!(swapped.black_move, !board.black_move);
+        }
+
+        #[test]
+        fn test_canonical_symmetries(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let board = random_walk(OthelloBoard::new(), &choices);
+            let (canonical, symmetry) = board.canonical();
+            prop_assert_eq!(board.transformed(symmetry), canonical);
+            for other in SYMMETRIES {
+                prop_assert_eq!(board.transformed(other).canonical().0, canonical);
+            }
+            for tile in TILES {
+                prop_assert!(untransform_tile(symmetry, transform_tile(symmetry, tile)) == tile);
+            }
+        }
+
+        #[test]
+        fn test_mirror_round_trip(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let board = random_walk(OthelloBoard::new(), &choices);
+            for axis in [Axis::Horizontal, Axis::Vertical, Axis::Diagonal, Axis::AntiDiagonal] {
+                let mirrored = board.mirrored(axis);
+                prop_assert_eq!(mirrored.mirrored(axis), board);
+
+                // a move is legal exactly when its reflection is legal on the mirrored board
+                let mut moves: Vec<usize> = board.find_current_moves_smallvec().iter()
+                    .map(|mov| axis.reflect(*mov).index())
+                    .collect();
+                let mut mirrored_moves: Vec<usize> = mirrored.find_current_moves_smallvec().iter()
+                    .map(|mov| mov.index())
+                    .collect();
+                moves.sort();
+                mirrored_moves.sort();
+                prop_assert_eq!(moves, mirrored_moves);
+            }
+        }
+
+        #[test]
+        fn test_empty_notation_round_trip(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let board = random_walk(OthelloBoard::empty(), &choices);
+            let other_board = OthelloBoard::from_notation(&board.to_notation()).unwrap();
+            prop_assert_eq!(board, other_board);
+        }
+    }
+
+    // plays a legal move picked by each choice in turn, passing when forced, until the choices run out or the game ends
+    fn random_walk(mut board: OthelloBoard, choices: &[usize]) -> OthelloBoard {
+        for choice in choices {
+            if board.is_game_over() {
+                break;
+            }
+            if board.must_pass() {
+                board = board.make_pass();
+                continue;
+            }
+            let moves = board.find_current_moves_as_vec();
+            board = board.make_move(moves[choice % moves.len()]);
+        }
+        board
+    }
+
+    // an independent and deliberately naive move generator: a move is legal when the tile is empty and some direction
+    // from it passes over one or more opponent discs and then reaches a disc of the side to move
+    fn find_reference_moves(board: &OthelloBoard) -> Vec<Tile> {
+        let color = board.current_color();
+        let in_center_phase = CENTER.iter().any(|tile| board.get_tile(*tile) == Color::Empty);
+        let mut moves = vec![];
+        for tile in TILES {
+            if board.get_tile(tile) != Color::Empty {
+                continue;
+            }
+            if in_center_phase {
+                if CENTER.contains(&tile) {
+                    moves.push(tile);
+                }
+                continue;
+            }
+            let flanks = (-1..=1).flat_map(|dr| (-1..=1).map(move |dc| (dr, dc)))
+                .filter(|&(dr, dc)| dr != 0 || dc != 0)
+                .any(|(dr, dc)| {
+                    let mut passed = 0;
+                    let (mut row, mut col) = (tile.row as i32 + dr, tile.col as i32 + dc);
+                    while (0..8).contains(&row) && (0..8).contains(&col) {
+                        let other = board.get_tile(Tile::new(row as i8, col as i8));
+                        if other == color {
+                            return passed > 0;
+                        }
+                        if other == Color::Empty {
+                            return false;
+                        }
+                        passed += 1;
+                        row += dr;
+                        col += dc;
+                    }
+                    false
+                });
+            if flanks {
+                moves.push(tile);
+            }
+        }
+        moves
+    }
+
+    #[test]
+    fn test_moves_match_reference() {
+        let mut rng = StdRng::seed_from_u64(0x3E7E);
+        for start in [OthelloBoard::new(), OthelloBoard::empty()] {
+            for _ in 0..200 {
+                let mut board = start;
+                while !board.is_game_over() {
+                    let mut moves = board.find_current_moves_as_vec();
+                    moves.sort_by_key(|tile| tile.index());
+                    moves.dedup();
+                    let reference_moves = find_reference_moves(&board);
+                    assert!(moves == reference_moves, "moves differ on {}", board.to_notation());
+
+                    board = if moves.is_empty() {
+                        board.make_pass()
+                    } else {
+                        board.make_move(moves[rng.gen_range(0..moves.len())])
+                    };
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_disc_counts() {
+        let mut rng = StdRng::seed_from_u64(0xC0DE);
+        for _ in 0..50 {
+            let mut board = OthelloBoard::new();
+            while !board.is_game_over() {
+                let black = TILES.into_iter().filter(|tile| board.get_tile(*tile) == Color::Black).count();
+                let white = TILES.into_iter().filter(|tile| board.get_tile(*tile) == Color::White).count();
+                assert_eq!(board.disc_counts(), (black, white));
+                assert_eq!(board.count_tiles(Color::Empty), 64 - black - white);
+                assert_eq!(board.color_swapped().disc_counts(), (white, black));
+
+                let moves = board.find_current_moves_as_vec();
+                board = if moves.is_empty() {
+                    board.make_pass()
+                } else {
+                    board.make_move(moves[rng.gen_range(0..moves.len())])
+                };
+            }
+        }
+        let board = OthelloBoard::from_notation("4EB3E/3EBWB2E/WE5BE/E2W3B2E/2E2WB3E/E2WBW3E/3EBEW2E/2EBEW3E/B").unwrap();
+        assert_eq!(board.disc_counts(), (15, 11));
+    }
 
     #[test]
     fn test_to_notation() {
-        let board = OthelloBoard { board: 1495472766589663741892773636151968256, black_move: true };
-        let notation = "4EW3E/3EWBW2E/BE5WE/E2B3W2E/2E2BW3E/E2BWB3E/3EWEB2E/2EWEB3E/B";
+        let board = OthelloBoard {
+            board: 1495472766589663741892773636151968256, black_move: true, black_count: 15, white_count: 11
+        };
+        let notation = "4EB3E/3EBWB2E/WE5BE/E2W3B2E/2E2WB3E/E2WBW3E/3EBEW2E/2EBEW3E/B";
         let other_notation = board.to_notation();
 
         assert_eq!(notation, other_notation);
+        assert_eq!(OthelloBoard::from_notation(notation).unwrap().raw(), 1495472766589663741892773636151968256);
     }
 
     #[test]
     fn test_from_notation() {
-        let board = OthelloBoard { board: 1495472766589663741892773636151968256, black_move: true };
-        let notation = "4EW3E/3EWBW2E/BE5WE/E2B3W2E/2E2BW3E/E2BWB3E/3EWEB2E/2EWEB3E/B";
-        let other_board = OthelloBoard::from_notation(&notation).unwrap();
+        let board = OthelloBoard {
+            board: 1495472766589663741892773636151968256, black_move: true, black_count: 15, white_count: 11
+        };
+        let notation = "4EB3E/3EBWB2E/WE5BE/E2W3B2E/2E2WB3E/E2WBW3E/3EBEW2E/2EBEW3E/B";
+        let other_board = OthelloBoard::from_notation(notation).unwrap();
 
         eprintln!("{}\n{}", board, other_board);
 
         assert_eq!(board, other_board);
+
+        // lowercase symbols read the same, and the notation is still written in uppercase
+        let lower_board = OthelloBoard::from_notation(&notation.to_lowercase()).unwrap();
+        assert_eq!(board, lower_board);
+        assert_eq!(lower_board.to_notation(), notation);
+        let mixed_board = OthelloBoard::from_notation("4eB3E/3EbWb2e/WE5BE/E2W3B2E/2E2WB3E/E2WBW3E/3EBEW2E/2EBEW3E/b");
+        assert_eq!(board, mixed_board.unwrap());
+    }
+
+    #[test]
+    fn test_try_from_notation() {
+        let board: OthelloBoard = "8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B".try_into().unwrap();
+        assert_eq!(board, OthelloBoard::new());
+        assert_eq!(OthelloBoard::try_from("8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/W"), Ok(OthelloBoard::new().make_pass()));
+        assert!(matches!(OthelloBoard::try_from("8E/8E/B"), Err(ParseError::InvalidNotation(_))));
+    }
+
+    #[test]
+    fn test_from_notation_rows() {
+        let err = OthelloBoard::from_notation("8E/8E/8E/B").unwrap_err();
+        assert_eq!(err.to_string(), "Must have exactly 8 rows");
+
+        let err = OthelloBoard::from_notation("8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/8E/B").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot have more than 8 rows");
+
+        let err = OthelloBoard::from_notation("8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/").unwrap_err();
+        assert_eq!(err.to_string(), "Must end with the turn symbol");
+
+        let err = OthelloBoard::from_notation("8E/8E/8E/3EWB2E/3EBW3E/8E/8E/8E/B").unwrap_err();
+        assert_eq!(err.to_string(), "Must have exactly 8 cols per row");
+
+        let board = OthelloBoard::from_notation("8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B").unwrap();
+        assert_eq!(board, OthelloBoard::new());
+    }
+
+    #[test]
+    fn test_color() {
+        for color in [Color::Empty, Color::White, Color::Black] {
+            assert_eq!(Color::from_u8(color.to_u8()), color);
+        }
+        assert_eq!(Color::Black.opposite(), Color::White);
+        assert_eq!(Color::White.opposite(), Color::Black);
+        assert_eq!(OthelloBoard::new().current_color(), Color::Black);
+    }
+
+    #[test]
+    fn test_symbol_round_trip() {
+        assert_eq!(Color::Black.to_symbol(), 'B');
+        assert_eq!(Color::White.to_symbol(), 'W');
+
+        let mut board = OthelloBoard::new();
+        for sym in ['E', 'B', 'W'] {
+            board.set_symbol(Tile::new(0, 0), sym).unwrap();
+            assert_eq!(board.get_symbol(Tile::new(0, 0)), sym);
+            assert_eq!(board.get_tile(Tile::new(0, 0)), Color::from_symbol(sym).unwrap());
+        }
+        assert!(board.set_symbol(Tile::new(0, 0), 'X').is_err());
+
+        // the start position has white on d4 and e5, and black on e4 and d5
+        let board = OthelloBoard::new();
+        assert_eq!(board.get_symbol(Tile::new(3, 3)), 'W');
+        assert_eq!(board.get_symbol(Tile::new(3, 4)), 'B');
+        assert_eq!(board.get_turn(), 'B');
+    }
+
+    #[test]
+    fn test_count_current_moves() {
+        assert_eq!(OthelloBoard::new().count_current_moves(), 4);
+
+        // walk a game and make sure moves found from multiple flanking discs are only counted once
+        let mut board = OthelloBoard::new();
+        let mut found_duplicate = false;
+        for _ in 0..20 {
+            let moves = board.find_current_moves_as_vec();
+            let mut distinct = moves.clone();
+            distinct.sort_by_key(|tile| tile.index());
+            distinct.dedup();
+
+            assert_eq!(board.count_current_moves(), distinct.len());
+            found_duplicate |= distinct.len() < moves.len();
+            board = board.make_move(moves[moves.len() / 2]);
+        }
+        assert!(found_duplicate);
+    }
+
+    #[test]
+    fn test_find_current_moves_smallvec() {
+        let mut board = OthelloBoard::new();
+        for _ in 0..20 {
+            let moves = board.find_current_moves_as_vec();
+            let small_moves = board.find_current_moves_smallvec();
+
+            assert_eq!(small_moves.len(), board.count_current_moves());
+            assert!(moves.iter().all(|mov| small_moves.contains(mov)));
+            board = board.make_move(moves[moves.len() / 2]);
+        }
+    }
+
+    #[test]
+    fn test_try_move() {
+        let board = OthelloBoard::new();
+        let next = board.try_move("d3").unwrap();
+        assert!(next == board.make_move(Tile::new(2, 3)));
+
+        assert!(matches!(board.try_move("a1"), Err(ParseError::IllegalMove(_))));
+        assert!(matches!(board.try_move("z9"), Err(ParseError::InvalidTile(_))));
+    }
+
+    #[test]
+    fn test_checked_make_move() {
+        let board = OthelloBoard::new();
+        assert!(board.checked_make_move(Tile::new(2, 3)) == Some(board.make_move(Tile::new(2, 3))));
+
+        // a1 flanks nothing and d4 is already occupied
+        assert!(board.checked_make_move(Tile::new(0, 0)).is_none());
+        assert!(board.checked_make_move(Tile::new(3, 3)).is_none());
+
+        // placing into the empty center is legal even though it flips nothing
+        let empty = OthelloBoard::empty();
+        assert!(empty.checked_make_move(Tile::new(3, 3)).is_some());
+    }
+
+    #[test]
+    fn test_apply_move_matches_make_move() {
+        let mut rng = StdRng::seed_from_u64(0xA991);
+        for _ in 0..50 {
+            let mut board = OthelloBoard::new();
+            let mut applied = OthelloBoard::new();
+            while !board.is_game_over() {
+                let moves = board.find_current_moves_as_vec();
+                if moves.is_empty() {
+                    board = board.make_pass();
+                    applied = applied.make_pass();
+                    continue;
+                }
+                let mov = moves[rng.gen_range(0..moves.len())];
+                board = board.make_move(mov);
+                applied.apply_move(mov);
+                assert!(applied == board);
+                assert_eq!(applied.disc_counts(), board.disc_counts());
+            }
+        }
+
+        // an illegal move leaves the board as it was
+        let mut board = OthelloBoard::new();
+        assert!(board.try_apply_move_in("a1", CoordStyle::Algebraic).is_err());
+        assert!(board == OthelloBoard::new());
+        board.try_apply_move_in("d3", CoordStyle::Algebraic).unwrap();
+        assert!(board == OthelloBoard::new().make_move(Tile::new(2, 3)));
+    }
+
+    #[test]
+    fn test_find_flips() {
+        let board = OthelloBoard::new();
+        assert!(board.find_flips(Tile::new(2, 3)) == Some(vec![Tile::new(3, 3)]));
+        assert!(board.find_flips(Tile::new(0, 0)).is_none());
+
+        // after d3 and c3, black at c4 flanks d4 against e4 but flanks nothing along the diagonal to d5
+        let board = board.make_move(Tile::new(2, 3)).make_move(Tile::new(2, 2));
+        assert!(board.find_flips(Tile::new(3, 2)) == Some(vec![Tile::new(3, 3)]));
+        assert_eq!(board.make_move(Tile::new(3, 2)).count_tiles(Color::Black), 5);
+        assert!(OthelloBoard::empty().find_flips(Tile::new(3, 3)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_moves_mask() {
+        let board = OthelloBoard::new();
+        let mask = board.find_current_moves_mask();
+        assert_eq!(mask.count_ones(), 4);
+        for mov in board.find_current_moves_as_vec() {
+            assert_ne!(mask & (1 << mov.index()), 0);
+        }
+        assert_eq!(board.find_potential_moves_mask(Color::White).count_ones(), 4);
+        assert_ne!(board.find_potential_moves_mask(Color::White), mask);
+    }
+
+    #[test]
+    fn test_flipped_mask_matches_make_move() {
+        let mut rng = StdRng::seed_from_u64(0xF11B);
+        for _ in 0..100 {
+            let mut board = OthelloBoard::new();
+            while !board.is_game_over() {
+                for color in [Color::Black, Color::White] {
+                    assert_eq!(board.discs_mask(color).count_ones() as usize, board.count_tiles(color));
+                }
+                for tile in TILES {
+                    let flipped = board.flipped_mask(tile, board.current_color());
+                    let child = board.make_move(tile);
+                    let expected = TILES.into_iter()
+                        .filter(|other| *other != tile && child.get_tile(*other) != board.get_tile(*other))
+                        .fold(0u64, |mask, other| mask | (1 << other.index()));
+                    if board.get_tile(tile) == Color::Empty {
+                        assert_eq!(flipped, expected, "flips differ for {} on {}", tile, board.to_notation());
+                    } else {
+                        assert_eq!(flipped, 0);
+                    }
+                    // a move is legal exactly when it flips something
+                    assert_eq!(flipped != 0, board.find_current_moves_mask() & (1 << tile.index()) != 0);
+                }
+
+                let moves = board.find_current_moves_as_vec();
+                board = if moves.is_empty() {
+                    board.make_pass()
+                } else {
+                    board.make_move(moves[rng.gen_range(0..moves.len())])
+                };
+            }
+        }
+    }
+
+    #[test]
+    fn test_orthogonal_directions() {
+        // black at a1 and h8 can each flank a white disc only along a diagonal
+        let mut board = OthelloBoard::new();
+        board.set_tile(Tile::new(0, 0), Color::Black);
+        board.set_tile(Tile::new(1, 1), Color::White);
+        board.set_tile(Tile::new(7, 7), Color::Black);
+        board.set_tile(Tile::new(6, 6), Color::White);
+
+        let mut all = vec![];
+        board.find_potential_moves(Color::Black, |mov| all.push(mov));
+        let mut orthogonal = vec![];
+        board.find_potential_moves_with(Color::Black, &ORTHOGONAL_DIRECTIONS, |mov| orthogonal.push(mov));
+
+        // c3 and f6 are only reachable by flanking diagonally
+        assert!(all.contains(&Tile::new(2, 2)) && all.contains(&Tile::new(5, 5)));
+        assert!(!orthogonal.contains(&Tile::new(2, 2)) && !orthogonal.contains(&Tile::new(5, 5)));
+        assert!(orthogonal.iter().all(|mov| all.contains(mov)));
+
+        // playing c3 flips b2 against a1 normally, but not when only orthogonal flanks count
+        let mut board = OthelloBoard::empty();
+        for tile in CENTER {
+            board.set_tile(tile, Color::White);
+        }
+        board.set_tile(Tile::new(0, 0), Color::Black);
+        board.set_tile(Tile::new(1, 1), Color::White);
+        let mov = Tile::new(2, 2);
+        assert!(board.make_move(mov).get_tile(Tile::new(1, 1)) == Color::Black);
+        assert!(board.make_move_with(mov, &ORTHOGONAL_DIRECTIONS).get_tile(Tile::new(1, 1)) == Color::White);
+    }
+
+    #[test]
+    fn test_perft() {
+        // the well known counts for the standard starting position
+        let counts = [1, 4, 12, 56, 244, 1396, 8200, 55092];
+        for (depth, count) in counts.into_iter().enumerate() {
+            assert_eq!(OthelloBoard::new().perft(depth as u32), count);
+        }
+    }
+
+    #[test]
+    fn test_pass() {
+        assert!(!OthelloBoard::new().must_pass());
+        assert!(!OthelloBoard::new().is_game_over());
+
+        // black's only disc is flanked against the edge so it has no moves, but white can still flank it
+        let mut board = OthelloBoard::new();
+        for tile in CENTER {
+            board.set_tile(tile, Color::White);
+        }
+        board.set_tile(Tile::new(0, 0), Color::White);
+        board.set_tile(Tile::new(0, 1), Color::White);
+        board.set_tile(Tile::new(0, 2), Color::Black);
+
+        assert!(board.must_pass());
+        assert!(!board.make_pass().must_pass());
+        assert!(!board.is_game_over());
+
+        // once white takes the black disc nobody has a move left
+        let board = board.make_pass().make_move(Tile::new(0, 3));
+        assert!(!board.must_pass());
+        assert!(board.is_game_over());
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let board = OthelloBoard::new();
+        assert_eq!(board.neighbors(Tile::new(0, 0)).len(), 3);
+        assert_eq!(board.neighbors(Tile::new(7, 7)).len(), 3);
+        assert_eq!(board.neighbors(Tile::new(0, 3)).len(), 5);
+        assert_eq!(board.neighbors(Tile::new(4, 7)).len(), 5);
+        assert_eq!(board.neighbors(Tile::new(3, 3)).len(), 8);
+
+        let neighbors = board.neighbors(Tile::new(0, 7));
+        for tile in [Tile::new(0, 6), Tile::new(1, 6), Tile::new(1, 7)] {
+            assert!(neighbors.contains(&tile));
+        }
+
+        // the start position's discs are ringed by empty tiles, while a full board has none anywhere
+        assert!(board.has_empty_neighbor(Tile::new(3, 3)));
+        let mut full = board;
+        for tile in TILES {
+            full.set_tile(tile, Color::Black);
+        }
+        assert!(TILES.iter().all(|tile| !full.has_empty_neighbor(*tile)));
+        full.set_tile(Tile::new(0, 0), Color::Empty);
+        assert!(full.has_empty_neighbor(Tile::new(1, 1)) && !full.has_empty_neighbor(Tile::new(2, 2)));
+    }
+
+    #[test]
+    fn test_game_length() {
+        let mut rng = StdRng::seed_from_u64(0x60);
+        for _ in 0..50 {
+            let mut board = OthelloBoard::new();
+            let (mut moves, mut plies) = (0, 0);
+            while !board.is_game_over() {
+                assert!(plies < MAX_GAME_PLIES);
+                let legal = board.find_current_moves_as_vec();
+                board = if legal.is_empty() {
+                    board.make_pass()
+                } else {
+                    moves += 1;
+                    board.make_move(legal[rng.gen_range(0..legal.len())])
+                };
+                plies += 1;
+                assert_eq!(board.moves_played(), moves);
+            }
+            assert!(moves <= MAX_GAME_MOVES && plies <= MAX_GAME_PLIES);
+        }
+        assert_eq!(OthelloBoard::new().moves_played(), 0);
+        assert_eq!(OthelloBoard::empty().moves_played(), 0);
+    }
+
+    #[test]
+    fn test_empty() {
+        let board = OthelloBoard::empty();
+        assert_eq!(board.count_tiles(Color::Empty), 64);
+        assert!(board.black_move);
+        assert!(!board.must_pass() && !board.is_game_over());
+
+        // the first four moves fill the center, then play continues under the normal flanking rules
+        let mut board = board;
+        for _ in 0..4 {
+            let moves = board.find_current_moves_as_vec();
+            assert!(moves.iter().all(|mov| CENTER.contains(mov)));
+            board = board.make_move(moves[0]);
+        }
+        assert_eq!(board.count_tiles(Color::Empty), 60);
+        assert!(board.find_current_moves_as_vec().iter().all(|mov| !CENTER.contains(mov)));
+    }
+
+    #[test]
+    fn test_display_alternate() {
+        let board = OthelloBoard::new();
+        let expected = "  a b c d e f g h \n\
+            1 · · · · · · · · \n\
+            2 · · · · · · · · \n\
+            3 · · · * · · · · \n\
+            4 · · * ○ ● · · · \n\
+            5 · · · ● ○ * · · \n\
+            6 · · · · * · · · \n\
+            7 · · · · · · · · \n\
+            8 · · · · · · · · \n";
+
+        assert_eq!(expected, format!("{:#}", board));
     }
 }
\ No newline at end of file
@@ -0,0 +1,133 @@
+/*
+ * Copyright (c) Joseph Prichard 2022.
+ */
+
+use std::collections::HashMap;
+use crate::board::{transform_tile, untransform_tile, OthelloBoard};
+use crate::errors::{ParseError, ParseResult};
+use crate::tile::{CoordStyle, Tile};
+
+#[derive(Copy, Clone)]
+pub struct BookMove {
+    pub tile: Tile,
+    pub count: u32,
+}
+
+// moves recorded from played games, keyed by canonical position so every symmetry of a position shares its entry
+pub struct OpeningBook {
+    entries: HashMap<OthelloBoard, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self { entries: HashMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // records that the move was played from the position, failing if the move is not legal there
+    pub fn add(&mut self, board: &OthelloBoard, mov: Tile) -> ParseResult<()> {
+        if board.checked_make_move(mov).is_none() {
+            return Err(ParseError::IllegalMove("Not a valid move".into()))
+        }
+        let (canonical, symmetry) = board.canonical();
+        let tile = transform_tile(symmetry, mov);
+        let moves = self.entries.entry(canonical).or_default();
+        match moves.iter_mut().find(|book_move| book_move.tile == tile) {
+            Some(book_move) => book_move.count += 1,
+            None => moves.push(BookMove { tile, count: 1 }),
+        }
+        Ok(())
+    }
+
+    // the moves recorded for the position, translated back onto the board as given, most played first
+    pub fn find_moves(&self, board: &OthelloBoard) -> Vec<BookMove> {
+        let (canonical, symmetry) = board.canonical();
+        let mut moves: Vec<BookMove> = self.entries.get(&canonical)
+            .map(|moves| moves.iter()
+                .map(|book_move| BookMove { tile: untransform_tile(symmetry, book_move.tile), count: book_move.count })
+                .collect())
+            .unwrap_or_default();
+        moves.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tile.index().cmp(&b.tile.index())));
+        moves
+    }
+
+    // one line per canonical position with each recorded move and how often it was played, sorted so the output is stable
+    pub fn export(&self, style: CoordStyle) -> String {
+        let mut lines: Vec<String> = self.entries.iter()
+            .map(|(board, moves)| {
+                let mut line = board.to_notation();
+                for book_move in moves {
+                    line.push_str(&format!(" {}={}", style.format(book_move.tile), book_move.count));
+                }
+                line
+            })
+            .collect();
+        lines.sort();
+        lines.join("\n")
+    }
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Axis, OthelloBoard};
+    use crate::book::OpeningBook;
+    use crate::errors::ParseError;
+    use crate::tile::{CoordStyle, Tile};
+
+    #[test]
+    fn test_symmetric_positions_share_entries() {
+        let mut book = OpeningBook::new();
+        let board = OthelloBoard::new();
+        // all four opening moves are reflections of each other
+        for mov in board.find_current_moves_as_vec() {
+            book.add(&board, mov).unwrap();
+        }
+        book.add(&board, Tile::new(2, 3)).unwrap();
+        assert_eq!(book.len(), 1);
+
+        let moves = book.find_moves(&board);
+        assert_eq!(moves.len(), 4);
+        assert!(moves.iter().all(|book_move| board.checked_make_move(book_move.tile).is_some()));
+        assert_eq!(moves[0].count, 2);
+        assert!(moves[0].tile == Tile::new(2, 3));
+
+        // after d3 the mirrored position across the diagonal is reached by c4 instead
+        let mut book = OpeningBook::new();
+        let after = board.make_move(Tile::new(2, 3));
+        book.add(&after, Tile::new(2, 2)).unwrap();
+        let mirrored = after.mirrored(Axis::Diagonal);
+        let moves = book.find_moves(&mirrored);
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].tile == Axis::Diagonal.reflect(Tile::new(2, 2)));
+    }
+
+    #[test]
+    fn test_export() {
+        let mut book = OpeningBook::new();
+        assert!(book.is_empty());
+        assert_eq!(book.export(CoordStyle::Algebraic), "");
+
+        let board = OthelloBoard::new();
+        book.add(&board, Tile::new(2, 3)).unwrap();
+        book.add(&board, Tile::new(2, 3)).unwrap();
+        book.add(&board.make_move(Tile::new(2, 3)), Tile::new(2, 2)).unwrap();
+        let export = book.export(CoordStyle::Algebraic);
+        assert_eq!(export.lines().count(), 2);
+        assert!(export.lines().any(|line| line.ends_with("=2")));
+
+        assert!(matches!(book.add(&board, Tile::new(0, 0)), Err(ParseError::IllegalMove(_))));
+    }
+}
@@ -3,39 +3,67 @@
  */
 
 use std::{io::BufWriter, io::Write, mem};
+use crate::logger;
+use crate::logger::LogLevel;
 
 const CACHE_SIZE: usize = (2i32.pow(12) + 1) as usize;
+const EVAL_CACHE_SIZE: usize = (2i32.pow(14) + 1) as usize;
 
 type CacheLine = [Option<CacheNode>; 2];
 type Cache = [CacheLine; CACHE_SIZE];
 
+// how a cached heuristic relates to the true score, a search that fails low or high only proves a bound on it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the true score is at least the heuristic
+    Lower,
+    // the true score is at most the heuristic
+    Upper,
+}
+
 #[derive(Copy, Clone)]
 pub struct CacheNode {
     pub key: i64,
     pub heuristic: f32,
     pub depth: u32,
+    pub bound: Bound,
 }
 
 impl CacheNode {
-    pub fn new(key: i64, heuristic: f32, depth: u32) -> Self {
-        Self { key, heuristic, depth }
+    pub fn new(key: i64, heuristic: f32, depth: u32, bound: Bound) -> Self {
+        Self { key, heuristic, depth, bound }
     }
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TtStats {
+    pub hits: u32,
+    pub misses: u32,
+    // puts that pushed a node for another position out of the table
+    pub collisions: u32,
+    // the fraction of slots holding a node
+    pub fill_factor: f32,
+    // the fraction of lookups that found a node, 0 if there were none
+    pub hit_rate: f32,
+}
+
 pub struct TranspositionTable {
     cache: Box<Cache>,
     hits: u32,
     misses: u32,
+    collisions: u32,
 }
 
 impl TranspositionTable {
     pub fn new() -> Self {
-        eprintln!("Cache size: {} bytes", mem::size_of::<Cache>());
+        logger::info(&format!("Cache size: {} bytes", mem::size_of::<Cache>()));
         // each cache line has 2 elements, one being "replace by depth" and one being "replace always"
         Self {
             cache: Box::new([[None; 2]; CACHE_SIZE]),
             hits: 0,
             misses: 0,
+            collisions: 0,
         }
     }
 
@@ -43,24 +71,34 @@ impl TranspositionTable {
         self.cache.len() as i64
     }
 
+    // slot 0 holds the deepest node that has hashed into the line and slot 1 holds the most recent of the rest,
+    // so slot 0 is always at least as deep as slot 1 no matter what order the nodes arrive in
+    // a node for a key already in the line replaces the old one, so a line never holds two nodes for one position
     pub fn put(&mut self, node: CacheNode) {
-        let some_node = Some(node);
         let h = node.key % self.cache_len();
         // retrieve cache line
         let cache_line = &mut self.cache[h as usize];
-        // check if "replace by depth" is populated
-        if let Some(first_node) = cache_line[0] {
-            // populated, new node is better so we do replacement
-            if node.depth > first_node.depth {
-                cache_line[1] = Some(first_node);
-                cache_line[0] = some_node;
-            } else {
-                // new node is worse so it should be sent to "replace always"
-                cache_line[1] = some_node;
+        // drop any stale node for the same key, promoting slot 1 if slot 0 is freed
+        if cache_line[1].is_some_and(|n| n.key == node.key) {
+            cache_line[1] = None;
+        }
+        if cache_line[0].is_some_and(|n| n.key == node.key) {
+            cache_line[0] = cache_line[1].take();
+        }
+        // either way whatever is in slot 1 is pushed out of the table
+        if cache_line[1].is_some() {
+            self.collisions += 1;
+        }
+        match cache_line[0] {
+            // new node is no deeper so it should be sent to "replace always"
+            Some(first_node) if node.depth <= first_node.depth => {
+                cache_line[1] = Some(node);
+            }
+            // slot 0 is empty or the new node is deeper, so the old node is demoted to "replace always"
+            _ => {
+                cache_line[1] = cache_line[0];
+                cache_line[0] = Some(node);
             }
-        } else {
-            // not populated, so it can be used
-            cache_line[0] = some_node;
         }
     }
 
@@ -69,13 +107,11 @@ impl TranspositionTable {
         // retrieve cache line
         let cache_line = &self.cache[h as usize];
         // iterate through cache line
-        for opt_node in cache_line {
+        for node in cache_line.iter().flatten() {
             // if node is in cache line return it
-            if let Some(node) = opt_node {
-                if node.key == key {
-                    self.hits += 1;
-                    return Some(node);
-                }
+            if node.key == key {
+                self.hits += 1;
+                return Some(node);
             }
         }
         self.misses += 1;
@@ -89,6 +125,9 @@ impl TranspositionTable {
     }
 
     pub fn dump(&self) {
+        if !logger::enabled(LogLevel::Normal) {
+            return;
+        }
         let stderr = std::io::stderr().lock();
         let mut bw = BufWriter::new(stderr);
 
@@ -97,29 +136,186 @@ impl TranspositionTable {
         for cache_line in self.cache.iter() {
             match &cache_line[0] {
                 Some(node) => {
-                    write!(bw, "Slot1 {} {} {} ", node.key, node.heuristic, node.depth).unwrap()
+                    write!(bw, "Slot1 {} {} {} {:?} ", node.key, node.heuristic, node.depth, node.bound).unwrap()
                 },
                 None => write!(bw, "Slot1 Empty ").unwrap()
             };
             match &cache_line[1] {
                 Some(node) => {
-                    writeln!(bw, "Slot2 {} {} {}", node.key, node.heuristic, node.depth).unwrap()
+                    writeln!(bw, "Slot2 {} {} {} {:?}", node.key, node.heuristic, node.depth, node.bound).unwrap()
                 },
                 None => writeln!(bw, "Slot2 Empty").unwrap()
             };
         }
     }
 
+    pub fn stats(&self) -> TtStats {
+        let filled = self.cache.iter().flatten().filter(|slot| slot.is_some()).count();
+        let lookups = self.hits + self.misses;
+        TtStats {
+            hits: self.hits,
+            misses: self.misses,
+            collisions: self.collisions,
+            fill_factor: filled as f32 / (self.cache.len() * 2) as f32,
+            hit_rate: if lookups > 0 { self.hits as f32 / lookups as f32 } else { 0f32 },
+        }
+    }
+
     pub fn hits(&self) -> u32 {
-        self.hits
+        self.stats().hits
     }
 
     pub fn misses(&self) -> u32 {
-        self.misses
+        self.stats().misses
     }
 
     pub fn reset_counts(&mut self) {
         self.hits = 0;
         self.misses = 0;
+        self.collisions = 0;
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// memoizes static evaluations of leaves, which are often reached again through transpositions too shallow for the
+// transposition table to answer, each slot keeps the key so a colliding board is never given the wrong score
+pub struct EvalCache {
+    cache: Box<[Option<(i64, f32)>; EVAL_CACHE_SIZE]>,
+}
+
+impl EvalCache {
+    pub fn new() -> Self {
+        Self {
+            cache: Box::new([None; EVAL_CACHE_SIZE]),
+        }
+    }
+
+    pub fn get(&self, key: i64) -> Option<f32> {
+        match self.cache[(key % EVAL_CACHE_SIZE as i64) as usize] {
+            Some((slot_key, heuristic)) if slot_key == key => Some(heuristic),
+            _ => None
+        }
+    }
+
+    // the slot is always replaced, the newest leaves are the most likely to be visited again
+    pub fn put(&mut self, key: i64, heuristic: f32) {
+        self.cache[(key % EVAL_CACHE_SIZE as i64) as usize] = Some((key, heuristic));
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.fill(None);
     }
-}
\ No newline at end of file
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::{Bound, CacheNode, EvalCache, TranspositionTable, TtStats, CACHE_SIZE, EVAL_CACHE_SIZE};
+
+    // keys that all hash into the first cache line
+    fn line_key(i: i64) -> i64 {
+        i * CACHE_SIZE as i64
+    }
+
+    fn line(table: &TranspositionTable) -> [Option<(i64, u32)>; 2] {
+        table.cache[0].map(|slot| slot.map(|node| (node.key, node.depth)))
+    }
+
+    #[test]
+    fn test_deeper_node_takes_slot_0() {
+        let mut table = TranspositionTable::new();
+        table.put(CacheNode::new(line_key(1), 0f32, 3, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(1), 3)), None]);
+
+        // a deeper node demotes the old one to slot 1
+        table.put(CacheNode::new(line_key(2), 0f32, 5, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(2), 5)), Some((line_key(1), 3))]);
+
+        // shallower and equally deep nodes always replace slot 1
+        table.put(CacheNode::new(line_key(3), 0f32, 4, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(2), 5)), Some((line_key(3), 4))]);
+        table.put(CacheNode::new(line_key(4), 0f32, 5, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(2), 5)), Some((line_key(4), 5))]);
+    }
+
+    #[test]
+    fn test_same_key_replaces_node() {
+        let mut table = TranspositionTable::new();
+        table.put(CacheNode::new(line_key(1), 0f32, 5, Bound::Exact));
+        table.put(CacheNode::new(line_key(2), 0f32, 3, Bound::Exact));
+
+        // re-searching the slot 1 key deeper moves it to slot 0 without leaving a stale copy behind
+        table.put(CacheNode::new(line_key(2), 1f32, 7, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(2), 7)), Some((line_key(1), 5))]);
+
+        // a shallower result for the slot 0 key replaces it, and the deeper slot 1 node is promoted over it
+        table.put(CacheNode::new(line_key(2), 2f32, 2, Bound::Exact));
+        assert_eq!(line(&table), [Some((line_key(1), 5)), Some((line_key(2), 2))]);
+        assert_eq!(table.get(line_key(2)).unwrap().heuristic, 2f32);
+    }
+
+    #[test]
+    fn test_slot_0_is_deepest_in_any_order() {
+        let depths = [(1, 4), (2, 6), (3, 2), (4, 6), (5, 5)];
+        for rotation in 0..depths.len() {
+            let mut table = TranspositionTable::new();
+            for i in 0..depths.len() {
+                let (key, depth) = depths[(i + rotation) % depths.len()];
+                table.put(CacheNode::new(line_key(key), 0f32, depth, Bound::Exact));
+            }
+            let [first, second] = line(&table);
+            assert_eq!(first.unwrap().1, 6);
+            assert!(second.unwrap().1 <= first.unwrap().1);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut table = TranspositionTable::new();
+        assert_eq!(table.stats(), TtStats::default());
+
+        table.put(CacheNode::new(line_key(1), 0f32, 3, Bound::Exact));
+        table.put(CacheNode::new(line_key(2), 0f32, 2, Bound::Exact));
+        // a third node in a full line pushes one out
+        table.put(CacheNode::new(line_key(3), 0f32, 1, Bound::Exact));
+        // replacing a position with itself is not a collision
+        table.put(CacheNode::new(line_key(3), 0f32, 1, Bound::Exact));
+        table.get(line_key(1));
+        table.get(line_key(2));
+        table.get(line_key(3));
+        table.get(line_key(4));
+
+        let stats = table.stats();
+        assert_eq!((stats.hits, stats.misses, stats.collisions), (2, 2, 1));
+        assert_eq!(stats.hit_rate, 0.5f32);
+        assert_eq!(stats.fill_factor, 2f32 / (CACHE_SIZE * 2) as f32);
+        assert_eq!(table.hits(), 2);
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cache = EvalCache::new();
+        cache.put(7, 1.5f32);
+        assert_eq!(cache.get(7), Some(1.5f32));
+
+        // a key landing in the same slot replaces the old one instead of being answered with its score
+        let colliding_key = 7 + EVAL_CACHE_SIZE as i64;
+        assert_eq!(cache.get(colliding_key), None);
+        cache.put(colliding_key, -2f32);
+        assert_eq!(cache.get(7), None);
+        assert_eq!(cache.get(colliding_key), Some(-2f32));
+
+        cache.clear();
+        assert_eq!(cache.get(colliding_key), None);
+    }
+}
@@ -2,19 +2,110 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
-use std::process::exit;
-use std::sync::LazyLock;
-use crate::agent::{AgentConfig, OthelloAgent};
-use crate::board::OthelloBoard;
+use std::any::Any;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::agent::{AgentConfig, OthelloAgent, SearchLimits, Verdict};
+use crate::board::{Axis, Color, OthelloBoard, MAX_GAME_PLIES, SYMMETRIES};
+use crate::book::OpeningBook;
+use crate::eval;
+use crate::eval::{Phase, ScoringMode};
 use crate::errors::{ParseResult, ParseError};
-use crate::tile::Tile;
+use crate::hasher::ZHasher;
+use crate::logger;
+use crate::tile::{parse_transcript, CoordStyle, RankedTile, TILES};
 
-const MAX_LEVEL: usize = 6;
+pub const MAX_LEVEL: usize = 6;
+pub const LEVEL_DEPTHS: [u32; MAX_LEVEL] = [2, 3, 5, 7, 10, 15];
+const MIN_DEPTH: u32 = 2;
+// how far apart, relative to its size, the scores of symmetric positions can be before they count as different
+const SYMMETRY_EPSILON: f32 = 1e-4;
+// random moves are seeded so that games against them are reproducible unless another seed is given at startup
+const RANDOM_MOVE_SEED: u64 = 0x5EED;
+// optional features compiled into the engine, reported to clients by the info command
+const FEATURES: &[&str] = &[
+    #[cfg(feature = "serde")]
+    "serde",
+];
+
+// the newest version of the command and response grammar, each version keeps every command of the ones before it
+pub const PROTOCOL_VERSION: u32 = 2;
+// far longer than any command needs, the longest being a trace of a whole game
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 4096;
+// commands added after the first version of the protocol and the version they were added in, the rest are in every version
+const COMMAND_PROTOCOLS: &[(&str, u32)] = &[
+    ("tournament", 2),
+    ("atply", 2),
+    ("units", 2),
+    ("hashing", 2),
+    ("raw", 2),
+    ("bestreply", 2),
+];
+// flags added to a command after the command itself, with the version they were added in
+const FLAG_PROTOCOLS: &[(&str, &str, u32)] = &[
+    ("moves", "--flips", 2),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineStatus {
+    Ok,
+    Error,
+    Quit,
+}
 
 pub struct CommandHandler {
     agents: Vec<Option<OthelloAgent>>,
     configs: Vec<AgentConfig>,
     current_board: OthelloBoard,
+    // how tiles are written in both the commands and the responses
+    coord_style: CoordStyle,
+    // appends the rendered board to the responses of commands that change it
+    echo_board: bool,
+    // moves recorded from played games, lasting until the engine is closed
+    book: OpeningBook,
+    // picks the moves of the randmove command
+    rng: StdRng,
+    // the version of the protocol the client asked for, which limits the commands it can use
+    protocol: u32,
+    // adds the score of best move responses in centi-discs alongside the raw heuristic
+    centi_discs: bool,
+    // when set every level hashes with a copy of this one hasher, so a board has the same key at every level,
+    // otherwise each level generates its own keys
+    shared_hasher: Option<ZHasher>,
+    // lines longer than this many bytes are rejected before they are parsed
+    max_line_length: usize,
+}
+
+// the results of one level over a tournament, each game counted from that level's side
+#[derive(Default)]
+struct TournamentTally {
+    wins: u32,
+    losses: u32,
+    draws: u32,
+    moves: u32,
+    move_time: Duration,
+}
+
+impl TournamentTally {
+    // the disc difference is from the side of the level being tallied
+    fn record_game(&mut self, difference: f32) {
+        if difference > 0f32 {
+            self.wins += 1;
+        } else if difference < 0f32 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+impl Default for CommandHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CommandHandler  {
@@ -24,31 +115,39 @@ impl CommandHandler  {
             agents.push(None);
         }
 
-        let configs = vec![
-            AgentConfig::new(2),
-            AgentConfig::new(3),
-            AgentConfig::new(5),
-            AgentConfig::new(7),
-            AgentConfig::new(10),
-            AgentConfig::new(15),
-        ];
-        Self { agents, configs, current_board: OthelloBoard::new() }
+        let configs = LEVEL_DEPTHS.iter()
+            .map(|depth| AgentConfig::new(*depth))
+            .collect();
+        Self {
+            agents,
+            configs,
+            current_board: OthelloBoard::new(),
+            coord_style: CoordStyle::default(),
+            echo_board: false,
+            book: OpeningBook::new(),
+            rng: StdRng::seed_from_u64(RANDOM_MOVE_SEED),
+            protocol: PROTOCOL_VERSION,
+            centi_discs: false,
+            shared_hasher: None,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
     }
 
     fn get_optional_agent(&mut self, valid_level: usize) -> &mut Option<OthelloAgent>  {
         self.agents.get_mut(valid_level - 1)
-            .expect(&format!("Fatal error: couldn't access agent Level {}", valid_level))
+            .unwrap_or_else(|| panic!("Fatal error: couldn't access agent Level {}", valid_level))
     }
 
     // function that will lazily generate agents only when needed
     fn get_agent(&mut self, valid_level: usize) -> &mut OthelloAgent {
         let config = self.configs[valid_level - 1];
+        let hasher = self.shared_hasher.clone().unwrap_or_default();
         // get the agent for the validated level
         let agent = self.get_optional_agent(valid_level);
         match agent {
             None => {
                 // create an agent and return a mutable reference to it if none exists
-                *agent = Some(OthelloAgent::new(config));
+                *agent = Some(OthelloAgent::with_hasher(config, hasher));
                 agent.as_mut().unwrap()
             }
             // just return a mutable reference to the agent if it exists
@@ -56,115 +155,498 @@ impl CommandHandler  {
         }
     }
 
-    pub fn handle_line(&mut self, line: &str) {
+    // handles a line of input, reporting whether it failed or the engine should stop reading input
+    pub fn handle_line(&mut self, line: &str) -> LineStatus {
+        // quitting is signalled to the caller so the engine can shut down normally and drop its state
+        if line.split(' ').next() == Some("quit") {
+            logger::info("Quit engine");
+            return LineStatus::Quit;
+        }
         // handle the command and write back the data
-        match self.handle_command(line) {
-            Ok(result) => println!("{}", result),
-            Err(err) =>  println!("error {}", err.to_string())
+        match self.catch_command(line) {
+            Ok(Ok(result)) => {
+                println!("{}", result);
+                LineStatus::Ok
+            }
+            Ok(Err(err)) => {
+                println!("error {}", err);
+                LineStatus::Error
+            }
+            Err(message) => {
+                println!("error internal panic: {}", message);
+                LineStatus::Error
+            }
+        }
+    }
+
+    // handles the command, turning a panic into its message so one bad command doesn't end the session
+    fn catch_command(&mut self, line: &str) -> Result<ParseResult<String>, String> {
+        // the agents are kept, a search that unwound partway only leaves state the next search resets
+        // and the cache only ever holds the nodes that were fully searched
+        panic::catch_unwind(AssertUnwindSafe(|| self.handle_command(line))).map_err(|payload| {
+            let message = Self::panic_message(payload.as_ref());
+            logger::error(&format!("Recovered from a panic in {}: {}", line, message));
+            message
+        })
+    }
+
+    // panics carry either a static or a formatted message, anything else has no message to show
+    fn panic_message(payload: &(dyn Any + Send)) -> String {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            String::from("unknown panic")
         }
     }
 
+    // splits the command on spaces, except that an argument wrapped in double quotes is kept whole, spaces and all
+    fn tokenize(command_str: &str) -> ParseResult<Vec<&str>> {
+        let mut tokens = vec![];
+        let mut rest = command_str;
+        loop {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"')
+                    .ok_or_else(|| ParseError::InvalidArg("Quoted argument must end with a quote".into()))?;
+                tokens.push(&quoted[..end]);
+                rest = &quoted[end + 1..];
+                if rest.is_empty() {
+                    break;
+                }
+                rest = rest.strip_prefix(' ')
+                    .ok_or_else(|| ParseError::InvalidArg("Quoted argument must be followed by a space".into()))?;
+            } else {
+                match rest.find(' ') {
+                    Some(i) => {
+                        tokens.push(&rest[..i]);
+                        rest = &rest[i + 1..];
+                    }
+                    None => {
+                        tokens.push(rest);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
     fn handle_command(&mut self, command_str: &str) -> ParseResult<String> {
-        let tokens = command_str.split(" ").collect::<Vec<&str>>();
+        if command_str.len() > self.max_line_length {
+            return Err(ParseError::OutOfRange(format!("Line must be at most {} bytes", self.max_line_length)))
+        }
+        let tokens = Self::tokenize(command_str)?;
  
         if tokens.is_empty() {
-            return Err(ParseError::new("Must contain command name"))
+            return Err(ParseError::MissingArgs("Must contain command name".into()))
         }
         let name = tokens[0];
         let args = &tokens[1..tokens.len()];
+        let added_in = COMMAND_PROTOCOLS.iter().find(|(command, _)| *command == name).map_or(1, |(_, version)| *version);
+        if added_in > self.protocol {
+            return Err(ParseError::UnknownCommand(format!("Command is not in protocol version {}", self.protocol)))
+        }
+        let newer_flag = FLAG_PROTOCOLS.iter()
+            .find(|(command, flag, version)| *command == name && *version > self.protocol && args.contains(flag));
+        if let Some((_, flag, _)) = newer_flag {
+            return Err(ParseError::InvalidArg(format!("Flag {} is not in protocol version {}", flag, self.protocol)))
+        }
         let result = match name {
-            "quit" => Self::handle_quit(),
+            "protocol" => self.handle_protocol(args)?,
             "view" => self.handle_view(),
+            "info" => self.handle_info(),
+            "flip" => self.handle_flip(),
+            "mirror" => self.handle_mirror(args)?,
+            "reset" => self.handle_reset(args)?,
             "move" => self.handle_move(args)?,
+            "randmove" => self.handle_random_move(args)?,
             "moves" => self.handle_moves(args)?,
+            "count" => self.handle_count(args)?,
+            "raw" => self.handle_raw(args)?,
+            "flips" => self.handle_flips(args)?,
+            "check" => self.handle_check(args)?,
+            "phase" => self.handle_phase(args)?,
+            "compare" => self.handle_compare(args)?,
+            "mustpass" => self.handle_must_pass(args)?,
+            "perft-divide" => self.handle_perft_divide(args)?,
             "profile" => self.handle_profile(args)?,
             "best" => self.handle_best_command(args)?,
+            "searchdepth" => self.handle_search_depth_command(args)?,
             "ranked" => self.handle_ranked_command(args)?,
+            "bestmoves" => self.handle_best_moves_command(args)?,
+            "bestreply" => self.handle_best_reply_command(args)?,
+            "tournament" => self.handle_tournament_command(args)?,
+            "verdict" => self.handle_verdict_command(args)?,
+            "bothsides" => self.handle_both_sides_command(args)?,
+            "warm" => self.handle_warm_command(args)?,
+            "trace" => self.handle_trace_command(args)?,
+            "atply" => self.handle_at_ply_command(args)?,
+            "checksym" => self.handle_check_symmetry_command(args)?,
+            "config" => self.handle_config(args)?,
+            "addlevel" => self.handle_add_level(args)?,
+            "weights" => Self::handle_weights(args)?,
+            "coords" => self.handle_coords(args)?,
+            "units" => self.handle_units(args)?,
+            "echo" => self.handle_echo(args)?,
+            "book" => self.handle_book(args)?,
+            "hash" => self.handle_hash(args)?,
+            "hashing" => self.handle_hashing(args)?,
+            #[cfg(test)]
+            "panic" => panic!("deliberate panic at {}", args.join(" ")),
             _ => {
-                return Err(ParseError::new("Unknown command name"))
+                return Err(ParseError::UnknownCommand("Unknown command name".into()))
             }
         };
         Ok(result)
     }
 
-    fn handle_quit() -> ! {
-        eprintln!("Quit engine");
-        exit(1)
+    // reports the protocol version in use, or switches to an older one so a client can keep the grammar it was written for
+    fn handle_protocol(&mut self, args: &[&str]) -> ParseResult<String> {
+        if let Some(version_str) = args.first() {
+            self.protocol = match version_str.parse::<u32>() {
+                Ok(version) if (1..=PROTOCOL_VERSION).contains(&version) => version,
+                Ok(_) => {
+                    return Err(ParseError::OutOfRange(format!("Protocol version must be between 1 and {}", PROTOCOL_VERSION)))
+                }
+                Err(..) => return Err(ParseError::NotANumber("Protocol version must be an integer".into())),
+            };
+        }
+        Ok(format!("protocol {}", self.protocol))
+    }
+
+    fn handle_info(&self) -> String {
+        // each line of the handshake is a key value pair so clients can parse it line by line
+        let mut info_str = String::new();
+        info_str.push_str(&format!("info name {}\n", env!("CARGO_PKG_NAME")));
+        info_str.push_str(&format!("info version {}\n", env!("CARGO_PKG_VERSION")));
+        info_str.push_str(&format!("info protocol {}\n", self.protocol));
+        info_str.push_str(&format!("info max_level {}\n", self.configs.len()));
+        info_str.push_str(&format!("info hashing {}\n", if self.shared_hasher.is_some() { "shared" } else { "perlevel" }));
+        if self.protocol >= 2 {
+            info_str.push_str(&format!("info moves {}\n", self.current_board.moves_played()));
+        }
+        for (i, config) in self.configs.iter().enumerate() {
+            info_str.push_str(&format!(
+                "info level {} depth {} cache {}\n",
+                i + 1, config.max_search_depth(), if config.cache() { "on" } else { "off" }
+            ));
+        }
+        if FEATURES.is_empty() {
+            info_str.push_str("info features none");
+        } else {
+            info_str.push_str(&format!("info features {}", FEATURES.join(" ")));
+        }
+        info_str
     }
 
     fn handle_view(&self) -> String {
         self.current_board.to_notation()
     }
 
+    fn handle_reset(&mut self, args: &[&str]) -> ParseResult<String> {
+        self.current_board = match args.first() {
+            None => OthelloBoard::new(),
+            Some(&"--empty") => OthelloBoard::empty(),
+            Some(_) => {
+                return Err(ParseError::InvalidArg("Reset flag must be --empty".into()))
+            }
+        };
+        Ok(self.echo(self.current_board.to_notation(), &self.current_board))
+    }
+
+    fn handle_flip(&mut self) -> String {
+        // only the turn changes, the discs stay where they are
+        self.current_board.black_move = !self.current_board.black_move;
+        self.echo(self.current_board.to_notation(), &self.current_board)
+    }
+
+    fn handle_mirror(&mut self, args: &[&str]) -> ParseResult<String> {
+        let axis = match args.first() {
+            Some(&"horizontal") => Axis::Horizontal,
+            Some(&"vertical") => Axis::Vertical,
+            Some(&"diagonal") => Axis::Diagonal,
+            Some(&"antidiagonal") => Axis::AntiDiagonal,
+            Some(_) => {
+                return Err(ParseError::InvalidArg("Axis must be horizontal, vertical, diagonal or antidiagonal".into()))
+            }
+            None => {
+                return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+            }
+        };
+        self.current_board = self.current_board.mirrored(axis);
+        Ok(self.echo(self.current_board.to_notation(), &self.current_board))
+    }
+
     fn handle_move(&mut self, args: &[&str]) -> ParseResult<String> {
-        if args.len() < 1 {
-            return Err(ParseError::new("Needs at least 1 args"))
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
         }
 
-        let mov = Tile::from_str(args[0])?;
-        let (board, using_curr) = match args.get(1) {
+        let new_board = match args.get(1) {
+            Some(str) => OthelloBoard::from_notation(str)?.try_move_in(args[0], self.coord_style)?,
+            None => {
+                self.current_board.try_apply_move_in(args[0], self.coord_style)?;
+                self.current_board
+            }
+        };
+
+        let result = format!("tile {}", new_board.to_notation());
+        Ok(self.echo(result, &new_board))
+    }
+
+    // plays a uniformly random legal move, the weakest possible opponent to measure the agents against
+    fn handle_random_move(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (board, using_curr) = match args.first() {
             Some(str) => (OthelloBoard::from_notation(str)?, false),
-            None => (self.current_board, true), // copy out for safety
+            None => (self.current_board, true),
         };
-        
-        // check if the tile is a valid move or not
-        if !board.find_current_moves_as_vec().contains(&mov) {
-            return Err(ParseError::new("Not a valid move"))
+        if board.must_pass() {
+            return Ok(String::from("pass"))
+        }
+        let moves = board.find_current_moves_as_vec();
+        if moves.is_empty() {
+            return Ok(String::from("notile"))
         }
 
+        let mov = moves[self.rng.gen_range(0..moves.len())];
         let new_board = board.make_move(mov);
         if using_curr {
-            self.current_board = new_board
+            self.current_board = new_board;
         }
+        // unlike move the tile was not picked by the client, so it is named ahead of the board under its own keyword
+        let result = format!("randmove {} {}", self.coord_style.format(mov), new_board.to_notation());
+        Ok(self.echo(result, &new_board))
+    }
 
-        let result = format!("tile {}", new_board.to_notation());
-        Ok(result)
+    // follows the response with the board drawn for a terminal when echoing is on
+    fn echo(&self, response: String, board: &OthelloBoard) -> String {
+        if self.echo_board {
+            format!("{}\n{:#}", response, board)
+        } else {
+            response
+        }
     }
 
     fn handle_moves(&self, args: &[&str]) -> ParseResult<String> {
-        let board = match args.get(0) {
+        // optional leading flags, --for picks the color to generate for instead of the side to move and --flips adds
+        // how many discs each move flips
+        let mut color = None;
+        let mut with_flips = false;
+        let mut args = args;
+        loop {
+            match args.first() {
+                Some(&"--for") => {
+                    color = match args.get(1).map(|str| str.chars().collect::<Vec<char>>()).as_deref() {
+                        Some(['B']) => Some(Color::Black),
+                        Some(['W']) => Some(Color::White),
+                        Some(_) => return Err(ParseError::InvalidArg("Color must be B or W".into())),
+                        None => return Err(ParseError::MissingArgs("For flag needs a color".into())),
+                    };
+                    args = &args[2..];
+                }
+                Some(&"--flips") => {
+                    with_flips = true;
+                    args = &args[1..];
+                }
+                _ => break,
+            }
+        }
+        let board = match args.first() {
             Some(str) => OthelloBoard::from_notation(str)?,
             None => self.current_board, // copy out for convenience
         };
 
-        // construct a moves output as a space-sep string
+        // construct a moves output as a space-sep string, listing a move flanked from several directions once
+        let color = color.unwrap_or(board.current_color());
         let mut moves_str = String::from("moves ");
-        board.find_current_moves(|mov| {
-            moves_str.push_str(&mov.to_string());
+        for mov in board.find_potential_moves_smallvec(color) {
+            moves_str.push_str(&self.coord_style.format(mov));
+            if with_flips {
+                moves_str.push_str(&format!(":{}", board.flipped_mask(mov, color).count_ones()));
+            }
             moves_str.push(' ')
-        });
+        }
         Ok(moves_str)
     }
 
-    fn parse_level(level_str: &str) -> ParseResult<usize> {
+    fn handle_count(&self, args: &[&str]) -> ParseResult<String> {
+        let board = match args.first() {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+        Ok(format!("count {}", board.count_current_moves()))
+    }
+
+    // the internal integer the board is packed into, as written in the test fixtures
+    fn handle_raw(&self, args: &[&str]) -> ParseResult<String> {
+        let board = match args.first() {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+        Ok(format!("raw {}", board.raw()))
+    }
+
+    fn handle_flips(&self, args: &[&str]) -> ParseResult<String> {
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+        }
+        let mov = self.coord_style.parse(args[0])?;
+        let board = match args.get(1) {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+
+        let flips = board.find_flips(mov).ok_or_else(|| ParseError::IllegalMove("Not a valid move".into()))?;
+        let mut flips_str = String::from("flips ");
+        for tile in flips {
+            flips_str.push_str(&self.coord_style.format(tile));
+            flips_str.push(' ')
+        }
+        Ok(flips_str)
+    }
+
+    fn handle_check(&self, args: &[&str]) -> ParseResult<String> {
+        // the board comes first here since any number of tiles follow it, a board is told apart by its row slashes
+        let (board, tiles) = match args.first() {
+            Some(str) if str.contains('/') => (OthelloBoard::from_notation(str)?, &args[1..]),
+            _ => (self.current_board, args), // copy out for convenience
+        };
+        if tiles.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 tile".into()))
+        }
+
+        let moves_mask = board.find_current_moves_mask();
+        let mut check_str = String::new();
+        for (i, str) in tiles.iter().enumerate() {
+            let tile = self.coord_style.parse(str)?;
+            let status = if moves_mask & (1 << tile.index()) != 0 { "ok" } else { "bad" };
+            if i > 0 {
+                check_str.push(' ');
+            }
+            check_str.push_str(&format!("{}={}", str, status));
+        }
+        Ok(check_str)
+    }
+
+    fn handle_phase(&self, args: &[&str]) -> ParseResult<String> {
+        let board = match args.first() {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+        let phase_str = format!("phase {} empty {}", Phase::from_board(&board), board.count_tiles(Color::Empty));
+        if self.protocol >= 2 {
+            Ok(format!("{} moves {}", phase_str, board.moves_played()))
+        } else {
+            Ok(phase_str)
+        }
+    }
+
+    fn handle_compare(&self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 2 {
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        let old_board = OthelloBoard::from_notation(args[0])?;
+        let new_board = OthelloBoard::from_notation(args[1])?;
+
+        // one line per tile that changed, giving the symbol on the first board then the second
+        let mut lines = vec![];
+        for tile in TILES {
+            let (old_sym, new_sym) = (old_board.get_symbol(tile), new_board.get_symbol(tile));
+            if old_sym != new_sym {
+                lines.push(format!("diff {} {} {}", self.coord_style.format(tile), old_sym, new_sym));
+            }
+        }
+        if old_board.black_move != new_board.black_move {
+            lines.push(format!(
+                "diff turn {} {}", old_board.current_color().to_symbol(), new_board.current_color().to_symbol()
+            ));
+        }
+        if lines.is_empty() {
+            return Ok(String::from("same"))
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn handle_must_pass(&self, args: &[&str]) -> ParseResult<String> {
+        let board = match args.first() {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+        // a finished game has no pass to make, so it answers no as well
+        Ok(String::from(if board.must_pass() { "yes" } else { "no" }))
+    }
+
+    fn handle_perft_divide(&self, args: &[&str]) -> ParseResult<String> {
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+        }
+        // no game runs longer than MAX_GAME_PLIES, so a deeper perft would only count finished games again
+        let depth = match args[0].parse::<u32>() {
+            Ok(depth) if (1..=MAX_GAME_PLIES as u32).contains(&depth) => depth,
+            Ok(_) => {
+                return Err(ParseError::OutOfRange(format!("Perft depth must be between 1 and {}", MAX_GAME_PLIES)))
+            }
+            Err(..) => return Err(ParseError::NotANumber("Perft depth must be an integer".into())),
+        };
+        let board = match args.get(1) {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+
+        // one line per root move with the size of its subtree, so a wrong total can be traced to a single move
+        // the total is the sum of the lines, which a finished game has none of since perft counts it as one position
+        let mut divide_str = String::new();
+        let mut total = if board.is_game_over() { 1 } else { 0 };
+        let moves = board.find_current_moves_smallvec();
+        if moves.is_empty() && !board.is_game_over() {
+            let count = board.make_pass().perft(depth - 1);
+            divide_str.push_str(&format!("divide pass {}\n", count));
+            total += count;
+        }
+        for mov in moves {
+            let count = board.make_move(mov).perft(depth - 1);
+            divide_str.push_str(&format!("divide {} {}\n", self.coord_style.format(mov), count));
+            total += count;
+        }
+        divide_str.push_str(&format!("divide total {}", total));
+        Ok(divide_str)
+    }
+
+    fn parse_level(&self, level_str: &str) -> ParseResult<usize> {
         let level = match level_str.parse::<usize>() {
             Ok(level) => level,
             Err(..) => {
-                return Err(ParseError::new("Level must be an integer"))
+                return Err(ParseError::NotANumber("Level must be an integer".into()))
             }
         };
-        if level < 1 || level > MAX_LEVEL {
-            static ERR_MSG: LazyLock<String> = std::sync::LazyLock::new(|| format!("Level must be between 1 and {}", MAX_LEVEL));
-            return Err(ParseError::new(ERR_MSG.as_str()))
+        // levels can be added at runtime, so the bound is however many levels exist now
+        let level_count = self.configs.len();
+        if !(1..=level_count).contains(&level) {
+            return Err(ParseError::OutOfRange(format!("Level must be between 1 and {}", level_count)))
         }
         Ok(level)
     }
 
     fn handle_profile(&mut self, args: &[&str]) -> ParseResult<String> {
         if args.len() < 2 {
-            return Err(ParseError::new("Needs at least 2 args"))
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        // the flag is checked first so a bad flag is reported even when the level is bad too
+        if !["log", "dump", "drop", "export"].contains(&args[0]) {
+            return Err(ParseError::InvalidArg("Profile flag must be log, dump, drop or export".into()))
         }
-        let level = Self::parse_level(args[1])?;
+        let level = self.parse_level(args[1])?;
         match args[0] {
             "log" => {
                 let agent = self.get_agent(level);
-                eprintln!("Logging runs for agent Level {}", level);
+                logger::info(&format!("Logging runs for agent Level {}", level));
                 agent.profiler.log_runs();
                 Ok(String::from("Logged runs data to stderr"))
             },
             "dump" => {
                 let agent = self.get_agent(level);
-                eprintln!("Dumping cache data for agent Level {}", level);
+                logger::info(&format!("Dumping cache data for agent Level {}", level));
                 agent.cache.dump();
                 Ok(String::from("Dumped cache data to stderr"))
             },
@@ -172,16 +654,260 @@ impl CommandHandler  {
                 *self.get_optional_agent(level) = None;
                 Ok(String::from(&format!("Dropped agent the Level {}", level)))
             }
-            _ => Err(ParseError::new("Profile flag must be dump or drop"))
+            "export" => {
+                let agent = self.get_agent(level);
+                Ok(agent.profiler.export_runs())
+            }
+            _ => unreachable!("Profile flag was already validated")
+        }
+    }
+
+    fn parse_toggle(toggle_str: &str) -> ParseResult<bool> {
+        match toggle_str {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(ParseError::InvalidArg("Toggle must be on or off".into()))
+        }
+    }
+
+    fn parse_temperature(temperature_str: &str) -> ParseResult<f32> {
+        match temperature_str.parse::<f32>() {
+            Ok(temperature) if temperature.is_finite() && temperature >= 0f32 => Ok(temperature),
+            _ => Err(ParseError::InvalidArg("Temperature must be a non-negative number".into()))
+        }
+    }
+
+    // a time limit of 0 means the search is never cut short
+    fn parse_time_limit(time_limit_str: &str) -> ParseResult<Option<Duration>> {
+        match time_limit_str.parse::<u64>() {
+            Ok(0) => Ok(None),
+            Ok(time_limit) => Ok(Some(Duration::from_millis(time_limit))),
+            Err(..) => Err(ParseError::NotANumber("Time limit must be a non-negative integer of ms".into()))
+        }
+    }
+
+    fn handle_config(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 3 {
+            return Err(ParseError::MissingArgs("Needs at least 3 args".into()))
+        }
+        let level = self.parse_level(args[0])?;
+        let config = self.configs[level - 1];
+        let config = match args[1] {
+            "quiescence" => config.with_quiescence(Self::parse_toggle(args[2])?),
+            "temperature" => config.with_temperature(Self::parse_temperature(args[2])?),
+            "cache" => config.with_cache(Self::parse_toggle(args[2])?),
+            "timeout" => config.with_time_limit(Self::parse_time_limit(args[2])?),
+            "contempt" => config.with_contempt(Self::parse_contempt(args[2])?),
+            "scoring" => config.with_scoring(Self::parse_scoring(args[2])?),
+            _ => {
+                return Err(ParseError::InvalidArg(
+                    "Config option must be quiescence, temperature, cache, timeout, contempt or scoring".into()
+                ))
+            }
+        };
+        self.configs[level - 1] = config;
+        // update the agent in place so it keeps its cache
+        if let Some(agent) = self.get_optional_agent(level) {
+            agent.set_config(config);
+        }
+        Ok(format!("Set {} to {} for agent Level {}", args[1], args[2], level))
+    }
+
+    fn parse_contempt(contempt_str: &str) -> ParseResult<f32> {
+        match contempt_str.parse::<f32>() {
+            Ok(contempt) if contempt.is_finite() => Ok(contempt),
+            _ => Err(ParseError::InvalidArg("Contempt must be a number".into()))
+        }
+    }
+
+    fn parse_scoring(scoring_str: &str) -> ParseResult<ScoringMode> {
+        match scoring_str {
+            "strict" => Ok(ScoringMode::Strict),
+            "empties" => Ok(ScoringMode::EmptiesToWinner),
+            _ => Err(ParseError::InvalidArg("Scoring must be strict or empties".into()))
+        }
+    }
+
+    fn parse_depth(depth_str: &str) -> ParseResult<u32> {
+        // a depth counts one more than the plies searched, so the shallowest search is depth 2, which runs no deepening
+        // iterations and plays the move whose child has the best static heuristic
+        match depth_str.parse::<u32>() {
+            Ok(depth) if depth >= MIN_DEPTH => Ok(depth),
+            Ok(_) => Err(ParseError::OutOfRange(format!("Depth must be at least {}", MIN_DEPTH))),
+            Err(..) => Err(ParseError::NotANumber("Depth must be an integer".into())),
+        }
+    }
+
+    // turns the caches off for every level, so the raw search can be benchmarked
+    pub fn disable_cache(&mut self) {
+        for config in self.configs.iter_mut() {
+            *config = config.with_cache(false);
+        }
+    }
+
+    // reseeds the random move generator, so a run of randmove commands can be replayed
+    pub fn set_seed(&mut self, seed: &str) -> ParseResult<()> {
+        let seed = seed.parse::<u64>().map_err(|_| ParseError::NotANumber("Seed must be a positive integer".into()))?;
+        self.rng = StdRng::seed_from_u64(seed);
+        Ok(())
+    }
+
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    // limits the length of the lines the handler accepts, so a client can't make the engine parse huge inputs
+    pub fn set_max_line_length(&mut self, length: &str) -> ParseResult<()> {
+        self.max_line_length = match length.parse::<usize>() {
+            Ok(length) if length > 0 => length,
+            Ok(_) => return Err(ParseError::OutOfRange("Max line length must be at least 1".into())),
+            Err(..) => return Err(ParseError::NotANumber("Max line length must be an integer".into())),
+        };
+        Ok(())
+    }
+
+    // makes every level hash with one hasher generated from the seed, so keys can be compared across levels
+    pub fn share_hasher(&mut self, seed: &str) -> ParseResult<()> {
+        let seed = seed.parse::<u64>().map_err(|_| ParseError::NotANumber("Seed must be a positive integer".into()))?;
+        self.set_hasher(Some(ZHasher::from_seed(seed)));
+        Ok(())
+    }
+
+    // the keys already in a cache are only valid for the hasher that made them, so every agent is dropped
+    // and lazily recreated with the new one
+    fn set_hasher(&mut self, hasher: Option<ZHasher>) {
+        self.shared_hasher = hasher;
+        for agent in self.agents.iter_mut() {
+            *agent = None;
+        }
+    }
+
+    fn handle_hashing(&mut self, args: &[&str]) -> ParseResult<String> {
+        match args.first() {
+            Some(&"shared") => match args.get(1) {
+                Some(seed) => self.share_hasher(seed)?,
+                None => self.set_hasher(Some(ZHasher::new())),
+            },
+            Some(&"perlevel") => self.set_hasher(None),
+            Some(_) => {
+                return Err(ParseError::InvalidArg("Hashing must be shared or perlevel".into()))
+            }
+            None => {
+                return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+            }
+        }
+        Ok(format!("Set hashing to {}", args[0]))
+    }
+
+    // overrides the search depth of one level, or of every level if none is given
+    pub fn override_depth(&mut self, level: Option<&str>, depth: &str) -> ParseResult<()> {
+        let depth = Self::parse_depth(depth)?;
+        let levels = match level {
+            Some(level) => {
+                let level = self.parse_level(level)?;
+                level..=level
+            }
+            None => 1..=self.configs.len(),
+        };
+        for level in levels {
+            let config = self.configs[level - 1].with_max_search_depth(depth);
+            self.configs[level - 1] = config;
+            if let Some(agent) = self.get_optional_agent(level) {
+                agent.set_config(config);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_add_level(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+        }
+        let depth = Self::parse_depth(args[0])?;
+        let time_limit = match args.get(1) {
+            Some(time_limit_str) => Self::parse_time_limit(time_limit_str)?,
+            None => None,
+        };
+
+        // agents are created lazily, so only a slot is reserved for the new level
+        self.configs.push(AgentConfig::new(depth).with_time_limit(time_limit));
+        self.agents.push(None);
+        Ok(format!("Added agent Level {} with depth {}", self.configs.len(), depth))
+    }
+
+    fn handle_coords(&mut self, args: &[&str]) -> ParseResult<String> {
+        self.coord_style = match args.first() {
+            Some(&"algebraic") => CoordStyle::Algebraic,
+            Some(&"rowfirst") => CoordStyle::RowFirst,
+            Some(&"numeric") => CoordStyle::Numeric,
+            Some(_) => {
+                return Err(ParseError::InvalidArg("Coord style must be algebraic, rowfirst or numeric".into()))
+            }
+            None => {
+                return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+            }
+        };
+        Ok(format!("Set coords to {}", args[0]))
+    }
+
+    fn handle_units(&mut self, args: &[&str]) -> ParseResult<String> {
+        self.centi_discs = match args.first() {
+            Some(&"raw") => false,
+            Some(&"centidisc") => true,
+            Some(_) => {
+                return Err(ParseError::InvalidArg("Units must be raw or centidisc".into()))
+            }
+            None => {
+                return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+            }
+        };
+        Ok(format!("Set units to {}", args[0]))
+    }
+
+    fn handle_echo(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 2 {
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        if args[0] != "board" {
+            return Err(ParseError::InvalidArg("Echo option must be board".into()))
+        }
+        self.echo_board = Self::parse_toggle(args[1])?;
+        Ok(format!("Set echo board to {}", args[1]))
+    }
+
+    fn handle_book(&mut self, args: &[&str]) -> ParseResult<String> {
+        match args.first() {
+            Some(&"add") => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+                }
+                let mov = self.coord_style.parse(args[1])?;
+                let board = match args.get(2) {
+                    Some(str) => OthelloBoard::from_notation(str)?,
+                    None => self.current_board, // copy out for convenience
+                };
+                self.book.add(&board, mov)?;
+                Ok(format!("Added {} to the book, {} positions", args[1], self.book.len()))
+            }
+            Some(&"export") => Ok(self.book.export(self.coord_style)),
+            Some(_) => Err(ParseError::InvalidArg("Book flag must be add or export".into())),
+            None => Err(ParseError::MissingArgs("Needs at least 1 args".into())),
+        }
+    }
+
+    fn handle_weights(args: &[&str]) -> ParseResult<String> {
+        match args.first() {
+            Some(&"show") => Ok(format!("weights {}", eval::WEIGHTS)),
+            _ => Err(ParseError::InvalidArg("Weights flag must be show".into()))
         }
     }
 
     fn extract_agent_args(&self, args: &[&str]) -> ParseResult<(usize, OthelloBoard)> {
-        if args.len() < 1 {
-            return Err(ParseError::new("Needs at least 1 args"))
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
         }
 
-        let level = Self::parse_level(args[0])?;
+        let level = self.parse_level(args[0])?;
         let board = match args.get(1) {
             Some(str) => OthelloBoard::from_notation(str)?,
             None => self.current_board, // copy out for convenience
@@ -189,28 +915,936 @@ impl CommandHandler  {
         Ok((level, board))
     }
 
+    fn handle_hash(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (level, board) = self.extract_agent_args(args)?;
+        // unless the hasher is shared each level hashes with its own keys, so the key is only comparable with
+        // others from the same level
+        let hash_key = self.get_agent(level).hash(&board);
+        Ok(format!("hash {:016x}", hash_key))
+    }
+
     fn handle_best_command(&mut self, args: &[&str]) -> ParseResult<String> {
         let (level, board) = self.extract_agent_args(args)?;
+        Ok(self.find_best_response(level, &board))
+    }
 
-        let best_tile = self.get_agent(level).find_best_move(&board);
-        let result = match best_tile {
-            Some(tile) => format!("tile {}", tile.to_string()),
+    fn find_best_response(&mut self, level: usize, board: &OthelloBoard) -> String {
+        let (coord_style, centi_discs) = (self.coord_style, self.centi_discs);
+        Self::search_best_response(self.get_agent(level), board, coord_style, centi_discs)
+    }
+
+    fn search_best_response(
+        agent: &mut OthelloAgent, board: &OthelloBoard, coord_style: CoordStyle, centi_discs: bool
+    ) -> String {
+        // a forced pass is distinct from the game being over, which has no tile at all
+        if board.is_game_over() {
+            return String::from("notile")
+        }
+        if board.must_pass() {
+            return String::from("pass")
+        }
+
+        let result = agent.search(board, SearchLimits::best());
+        match result.best {
+            // the raw heuristic stays in its own field, so clients that read it are unaffected
+            Some(tile) if centi_discs => match eval::to_centi_discs(tile.heuristic, &board.make_move(tile.tile)) {
+                Some(centi_discs) => format!("tile {}, Centidiscs: {}", tile.format(coord_style), centi_discs),
+                None => format!("tile {}", tile.format(coord_style)),
+            },
+            Some(tile) => format!("tile {}", tile.format(coord_style)),
             None => String::from("notile"),
+        }
+    }
+
+    // searches with a throwaway agent to exactly the given number of plies, leaving the levels untouched
+    fn handle_search_depth_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.is_empty() {
+            return Err(ParseError::MissingArgs("Needs at least 1 args".into()))
+        }
+        let plies = match args[0].parse::<u32>() {
+            Ok(plies) if plies >= 1 => plies,
+            Ok(_) => return Err(ParseError::OutOfRange("Depth must be at least 1".into())),
+            Err(..) => return Err(ParseError::NotANumber("Depth must be an integer".into())),
         };
-        Ok(result)
+        let board = match args.get(1) {
+            Some(str) => OthelloBoard::from_notation(str)?,
+            None => self.current_board, // copy out for convenience
+        };
+
+        // a config's depth counts one more than the plies searched, since deepening stops a ply short of it
+        let mut agent = OthelloAgent::new(AgentConfig::new(plies + 1));
+        Ok(Self::search_best_response(&mut agent, &board, self.coord_style, self.centi_discs))
+    }
+
+    fn handle_both_sides_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (level, board) = self.extract_agent_args(args)?;
+        // the discs stay the same and only the side to move changes, as with the flip command
+        let mut black_board = board;
+        black_board.black_move = true;
+        let mut white_board = board;
+        white_board.black_move = false;
+
+        let black_response = self.find_best_response(level, &black_board);
+        let white_response = self.find_best_response(level, &white_board);
+        Ok(format!("black {}\nwhite {}", black_response, white_response))
     }
 
     fn handle_ranked_command(&mut self, args: &[&str]) -> ParseResult<String> {
         let (level, board) = self.extract_agent_args(args)?;
+        if board.must_pass() {
+            return Ok(String::from("pass"))
+        }
         
-        let ranked_tiles = self.get_agent(level).find_ranked_moves(&board);
-       
-        // add the ranked tiles to a space-sep string as a response
+        let ranked_tiles = self.get_agent(level).search(&board, SearchLimits::ranked()).ranked;
+
+        // add the ranked tiles to a space-sep string as a response, scored only when the units ask for centi-discs
         let mut tiles_str = String::from("tiles ");
         for r in ranked_tiles.iter() {
-            tiles_str.push_str(&r.tile.to_string());
+            if self.centi_discs {
+                tiles_str.push_str(&self.format_scored_tile(&board, r));
+            } else {
+                tiles_str.push_str(&self.coord_style.format(r.tile));
+            }
             tiles_str.push(' ');
         }
         Ok(tiles_str)
     }
-}
\ No newline at end of file
+
+    // a tile=heuristic pair, followed by /centidiscs when the units ask for them and the score counts discs
+    fn format_scored_tile(&self, board: &OthelloBoard, r: &RankedTile) -> String {
+        let mut tile_str = format!("{}={}", self.coord_style.format(r.tile), r.heuristic);
+        if self.centi_discs {
+            if let Some(centi_discs) = eval::to_centi_discs(r.heuristic, &board.make_move(r.tile)) {
+                tile_str.push_str(&format!("/{}", centi_discs));
+            }
+        }
+        tile_str
+    }
+
+    fn handle_warm_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 2 {
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        let depth = Self::parse_depth(args[1])?;
+        // the depth sits between the level and the board, so skip over it to reuse the agent args
+        let mut agent_args = vec![args[0]];
+        agent_args.extend(args.get(2));
+        let (level, board) = self.extract_agent_args(&agent_args)?;
+
+        let agent = self.get_agent(level);
+        let stats = agent.warm(&board, depth);
+        let fill_factor = agent.cache.stats().fill_factor;
+        Ok(format!(
+            "Warmed agent Level {} to depth {} with {} nodes, cache fill {:.3}",
+            level, depth, stats.nodes, fill_factor
+        ))
+    }
+
+    fn handle_verdict_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (level, board) = self.extract_agent_args(args)?;
+        let verdict = match self.get_agent(level).evaluation_verdict(&board) {
+            Verdict::Winning => "winning",
+            Verdict::Losing => "losing",
+            Verdict::Unclear => "unclear",
+        };
+        Ok(format!("verdict {}", verdict))
+    }
+
+    fn handle_trace_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 2 {
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        let level = self.parse_level(args[0])?;
+
+        // the moves are replayed on a copy, so the trace never changes the current board
+        let mut board = self.current_board;
+        let mut trace_str = String::from("trace ");
+        for (i, mov) in args[1..].iter().enumerate() {
+            // a side with no moves passes before the next move is played, as moves are listed without passes
+            if board.must_pass() {
+                board = board.make_pass();
+            }
+            board.try_apply_move_in(mov, self.coord_style).map_err(|err| match err {
+                ParseError::IllegalMove(_) => ParseError::IllegalMove(format!("Move {} at {} is not legal", mov, i + 1)),
+                err => err,
+            })?;
+            let score = self.get_agent(level).evaluate_position(&board);
+            trace_str.push_str(&format!("{}={} ", mov, score));
+        }
+        Ok(trace_str)
+    }
+
+    // replays the start of a recorded game and searches the position it reached, leaving the current board alone
+    fn handle_at_ply_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 3 {
+            return Err(ParseError::MissingArgs("Needs at least 3 args".into()))
+        }
+        let moves = parse_transcript(args[0])?;
+        let plies = args[1].parse::<usize>()
+            .map_err(|_| ParseError::NotANumber("Ply must be an integer".into()))?;
+        if plies > moves.len() {
+            return Err(ParseError::OutOfRange(format!("Ply must be at most the {} moves in the transcript", moves.len())))
+        }
+        let level = self.parse_level(args[2])?;
+
+        let mut board = OthelloBoard::new();
+        for (i, mov) in moves[..plies].iter().enumerate() {
+            // transcripts leave passes out, so a side with no moves passes before the next move is played
+            if board.must_pass() {
+                board = board.make_pass();
+            }
+            board = board.checked_make_move(*mov)
+                .ok_or_else(|| ParseError::IllegalMove(format!("Move {} at {} is not legal", mov, i + 1)))?;
+        }
+        let (coord_style, centi_discs) = (self.coord_style, self.centi_discs);
+        Ok(Self::search_best_response(self.get_agent(level), &board, coord_style, centi_discs))
+    }
+
+    fn handle_check_symmetry_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (level, board) = self.extract_agent_args(args)?;
+        let agent = self.get_agent(level);
+
+        // every reflection and rotation of a position is the same position, so any score that differs is a bug
+        let expected = agent.evaluate_position(&board);
+        let mut mismatches = vec![];
+        for symmetry in SYMMETRIES.iter().skip(1) {
+            let score = agent.evaluate_position(&board.transformed(symmetry));
+            if (score - expected).abs() > SYMMETRY_EPSILON * expected.abs().max(1f32) {
+                let name = symmetry.iter().map(|axis| axis.to_string()).collect::<Vec<String>>().join("+");
+                mismatches.push(format!("checksym mismatch {} {} expected {}", name, score, expected));
+            }
+        }
+        if mismatches.is_empty() {
+            return Ok(format!("checksym ok {}", expected))
+        }
+        Ok(mismatches.join("\n"))
+    }
+
+    fn handle_best_moves_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 2 {
+            return Err(ParseError::MissingArgs("Needs at least 2 args".into()))
+        }
+        let count = match args[1].parse::<usize>() {
+            Ok(count) if count >= 1 => count,
+            Ok(_) => return Err(ParseError::OutOfRange("Move count must be at least 1".into())),
+            Err(..) => return Err(ParseError::NotANumber("Move count must be an integer".into())),
+        };
+        // the move count sits between the level and the board, so skip over it to reuse the agent args
+        let mut agent_args = vec![args[0]];
+        agent_args.extend(args.get(2));
+        let (level, board) = self.extract_agent_args(&agent_args)?;
+        if board.must_pass() {
+            return Ok(String::from("pass"))
+        }
+
+        let ranked_tiles = self.get_agent(level).search(&board, SearchLimits::ranked()).ranked;
+
+        // ranked tiles are sorted with the best move last, so take them from the back
+        let mut tiles_str = String::from("tiles ");
+        for r in ranked_tiles.iter().rev().take(count) {
+            tiles_str.push_str(&self.format_scored_tile(&board, r));
+            tiles_str.push(' ');
+        }
+        Ok(tiles_str)
+    }
+
+    // the best move and the opponent's best reply to it, each scored from the side that makes it
+    fn handle_best_reply_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        let (level, board) = self.extract_agent_args(args)?;
+        if board.is_game_over() {
+            return Ok(String::from("notile"))
+        }
+        if board.must_pass() {
+            return Ok(String::from("pass"))
+        }
+
+        let coord_style = self.coord_style;
+        let agent = self.get_agent(level);
+        let best = agent.find_best_move(&board).expect("Fatal error: a side with moves must have a best move");
+        let reply_board = board.make_move(best.tile);
+        let reply_str = if reply_board.is_game_over() {
+            String::from("notile")
+        } else if reply_board.must_pass() {
+            String::from("pass")
+        } else {
+            let reply = agent.find_best_move(&reply_board)
+                .expect("Fatal error: a side with moves must have a best move");
+            format!("{}={}", coord_style.format(reply.tile), Self::side_score(&reply_board, reply.heuristic))
+        };
+        let best_score = Self::side_score(&board, best.heuristic);
+        Ok(format!("move {}={} reply {}", coord_style.format(best.tile), best_score, reply_str))
+    }
+
+    // heuristics are from black's side, so white's are negated to be from the side to move
+    fn side_score(board: &OthelloBoard, heuristic: f32) -> f32 {
+        // subtracting from zero rather than negating keeps an even score from printing as -0
+        if board.black_move { heuristic } else { 0f32 - heuristic }
+    }
+
+    // plays the two levels against each other from the start position, swapping who moves first every game
+    fn handle_tournament_command(&mut self, args: &[&str]) -> ParseResult<String> {
+        if args.len() < 3 {
+            return Err(ParseError::MissingArgs("Needs at least 3 args".into()))
+        }
+        let levels = [self.parse_level(args[0])?, self.parse_level(args[1])?];
+        let games = match args[2].parse::<u32>() {
+            Ok(games) if games >= 1 => games,
+            Ok(_) => return Err(ParseError::OutOfRange("Game count must be at least 1".into())),
+            Err(..) => return Err(ParseError::NotANumber("Game count must be an integer".into())),
+        };
+
+        let mut tallies = [TournamentTally::default(), TournamentTally::default()];
+        for game in 0..games {
+            // the index into levels of the side playing black
+            let black_seat = (game % 2) as usize;
+            let mut board = OthelloBoard::new();
+            let mut plies = 0;
+            while !board.is_game_over() {
+                // a game that runs longer than any real one means pass handling is broken, so stop rather than spin
+                assert!(plies < MAX_GAME_PLIES, "Game ran past {} plies at {}", MAX_GAME_PLIES, board.to_notation());
+                plies += 1;
+                if board.must_pass() {
+                    board = board.make_pass();
+                    continue;
+                }
+                let seat = if board.black_move { black_seat } else { 1 - black_seat };
+                let start_time = Instant::now();
+                let best = self.get_agent(levels[seat]).find_best_move(&board);
+                tallies[seat].move_time += start_time.elapsed();
+                tallies[seat].moves += 1;
+                board = board.make_move(best.expect("a side that doesn't have to pass has a move").tile);
+            }
+
+            let difference = eval::find_disc_difference(&board);
+            tallies[black_seat].record_game(difference);
+            tallies[1 - black_seat].record_game(-difference);
+        }
+
+        let mut tournament_str = String::from("level wins losses draws avg_move_ms");
+        for (level, tally) in levels.iter().zip(tallies.iter()) {
+            let avg_move_ms = tally.move_time.as_secs_f64() * 1000f64 / tally.moves.max(1) as f64;
+            tournament_str.push_str(&format!(
+                "\n{} {} {} {} {:.3}", level, tally.wins, tally.losses, tally.draws, avg_move_ms
+            ));
+        }
+        Ok(tournament_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::agent::{AgentConfig, OthelloAgent};
+    use crate::board::OthelloBoard;
+    use crate::command::{
+        CommandHandler, LineStatus, DEFAULT_MAX_LINE_LENGTH, LEVEL_DEPTHS, MAX_LEVEL, PROTOCOL_VERSION
+    };
+    use crate::errors::ParseError;
+    use crate::eval;
+    use crate::eval::ScoringMode;
+    use crate::tile::{CoordStyle, Tile};
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(CommandHandler::tokenize("best 3").unwrap(), vec!["best", "3"]);
+        assert_eq!(CommandHandler::tokenize("view").unwrap(), vec!["view"]);
+        assert_eq!(CommandHandler::tokenize("a \"b c\" d").unwrap(), vec!["a", "b c", "d"]);
+        assert_eq!(CommandHandler::tokenize("a \"\"").unwrap(), vec!["a", ""]);
+
+        assert!(matches!(CommandHandler::tokenize("a \"b c"), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(CommandHandler::tokenize("a \"b\"c"), Err(ParseError::InvalidArg(_))));
+
+        // quoting a simple argument changes nothing
+        let mut handler = CommandHandler::new();
+        assert_eq!(handler.handle_command("count \"8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B\"").unwrap(), "count 4");
+    }
+
+    #[test]
+    fn test_protocol() {
+        let mut handler = CommandHandler::new();
+        assert_eq!(handler.handle_command("protocol").unwrap(), format!("protocol {}", PROTOCOL_VERSION));
+        assert_eq!(handler.handle_command("protocol 1").unwrap(), "protocol 1");
+        assert!(handler.handle_command("info").unwrap().contains("info protocol 1\n"));
+        assert!(!handler.handle_command("info").unwrap().contains("info moves"));
+        assert_eq!(handler.handle_command("phase").unwrap(), "phase opening empty 60");
+        // the commands and flags of the first version still work, but the ones added since are rejected
+        assert!(handler.handle_command("moves").unwrap().starts_with("moves "));
+        for command in ["tournament 1 2 1", "atply d3 1 1", "units centidisc", "hashing shared", "raw", "bestreply 1"] {
+            assert!(matches!(handler.handle_command(command), Err(ParseError::UnknownCommand(_))), "{}", command);
+        }
+        assert!(matches!(handler.handle_command("moves --flips"), Err(ParseError::InvalidArg(_))));
+        assert!(!handler.centi_discs);
+
+        handler.handle_command("protocol 2").unwrap();
+        assert_eq!(handler.handle_command("raw").unwrap(), format!("raw {}", OthelloBoard::new().raw()));
+        assert!(handler.handle_command("moves --flips").unwrap().starts_with("moves "));
+        assert!(handler.handle_command("info").unwrap().contains("info moves 0\n"));
+        assert_eq!(handler.handle_command("phase").unwrap(), "phase opening empty 60 moves 0");
+        handler.handle_command("protocol 1").unwrap();
+
+        assert!(matches!(handler.handle_command("protocol 0"), Err(ParseError::OutOfRange(_))));
+        let newer = format!("protocol {}", PROTOCOL_VERSION + 1);
+        assert!(matches!(handler.handle_command(&newer), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_command("protocol one"), Err(ParseError::NotANumber(_))));
+        assert_eq!(handler.protocol, 1);
+    }
+
+    #[test]
+    fn test_parse_level() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.parse_level("1").unwrap(), 1);
+        assert_eq!(handler.parse_level(&MAX_LEVEL.to_string()).unwrap(), MAX_LEVEL);
+
+        assert!(matches!(handler.parse_level("one"), Err(ParseError::NotANumber(_))));
+        assert!(matches!(handler.parse_level("-1"), Err(ParseError::NotANumber(_))));
+        assert!(matches!(handler.parse_level("0"), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.parse_level(&(MAX_LEVEL + 1).to_string()), Err(ParseError::OutOfRange(_))));
+    }
+
+    #[test]
+    fn test_best_moves() {
+        let mut handler = CommandHandler::new();
+        let board = "8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B";
+
+        assert_eq!(handler.handle_best_moves_command(&["2", "2", board]).unwrap().split_whitespace().count(), 3);
+        assert_eq!(handler.handle_best_moves_command(&["2", "10"]).unwrap().split_whitespace().count(), 5);
+        assert!(matches!(handler.handle_best_moves_command(&["2", "0"]), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_best_moves_command(&["2"]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_best_reply() {
+        let mut handler = CommandHandler::new();
+        let board = OthelloBoard::new();
+        let mut agent = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1]));
+        let best = agent.find_best_move(&board).unwrap();
+        let reply = agent.find_best_move(&board.make_move(best.tile)).unwrap();
+        // white replies, so its score is negated to be from white's side
+        let expected = format!("move {}={} reply {}={}", best.tile, best.heuristic, reply.tile, 0f32 - reply.heuristic);
+        assert_eq!(handler.handle_best_reply_command(&["2"]).unwrap(), expected);
+
+        // with white to move the move is negated and black's reply is not
+        let white_board = board.make_move(best.tile);
+        let mut agent = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1]));
+        let best = agent.find_best_move(&white_board).unwrap();
+        let reply = agent.find_best_move(&white_board.make_move(best.tile)).unwrap();
+        let expected = format!("move {}={} reply {}={}", best.tile, 0f32 - best.heuristic, reply.tile, reply.heuristic);
+        assert_eq!(handler.handle_best_reply_command(&["2", &white_board.to_notation()]).unwrap(), expected);
+
+        // either capture leaves white a disc it can't move with, so white must pass
+        let board = "EW6B/8E/8E/3E2B3E/3E2B3E/8E/8E/EW6B/B";
+        let response = handler.handle_best_reply_command(&["2", board]).unwrap();
+        assert!(response.starts_with("move a") && response.ends_with(" reply pass"), "{}", response);
+
+        // capturing the last white disc ends the game
+        let board = "8E/8E/8E/3E2B3E/3E2B3E/8E/8E/EW6B/B";
+        assert!(handler.handle_best_reply_command(&["2", board]).unwrap().ends_with(" reply notile"));
+
+        assert_eq!(handler.handle_best_reply_command(&["2", "EW6B/8B/8B/8B/8B/8B/8B/8B/W"]).unwrap(), "pass");
+        assert_eq!(handler.handle_best_reply_command(&["2", "8B/8B/8B/8B/8B/8B/8B/8B/W"]).unwrap(), "notile");
+        assert!(matches!(handler.handle_best_reply_command(&[]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_search_depth() {
+        let mut handler = CommandHandler::new();
+        let board = "2EW3B2W/6B2W/EB3W2BW/BWB2W3B/E2W5B/2W4BWB/7WB/3EB3WB/W";
+
+        // searching 3 plies is the same search as a level whose depth is one more
+        let best = OthelloAgent::new(AgentConfig::new(4)).find_best_move(&OthelloBoard::from_notation(board).unwrap());
+        let expected = format!("tile {}", best.unwrap());
+        assert_eq!(handler.handle_search_depth_command(&["3", board]).unwrap(), expected);
+        assert_eq!(handler.configs[2].max_search_depth(), LEVEL_DEPTHS[2]);
+        assert!(handler.get_optional_agent(3).is_none());
+
+        assert!(handler.handle_search_depth_command(&["1"]).unwrap().starts_with("tile "));
+        assert_eq!(handler.handle_search_depth_command(&["2", "3W5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B"]).unwrap(), "notile");
+        assert!(matches!(handler.handle_search_depth_command(&["0"]), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_search_depth_command(&["deep"]), Err(ParseError::NotANumber(_))));
+        assert!(matches!(handler.handle_search_depth_command(&[]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_tournament() {
+        let mut handler = CommandHandler::new();
+        let tournament = handler.handle_tournament_command(&["1", "2", "2"]).unwrap();
+        let lines = tournament.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "level wins losses draws avg_move_ms");
+
+        let tallies = lines[1..].iter()
+            .map(|line| line.split(' ').map(|field| field.parse::<f64>().unwrap()).collect::<Vec<f64>>())
+            .collect::<Vec<Vec<f64>>>();
+        assert_eq!((tallies[0][0], tallies[1][0]), (1f64, 2f64));
+        for tally in &tallies {
+            assert_eq!(tally[1] + tally[2] + tally[3], 2f64);
+            assert!(tally[4] >= 0f64);
+        }
+        // every game one level wins is a game the other loses
+        assert_eq!(tallies[0][1], tallies[1][2]);
+        assert_eq!(tallies[0][3], tallies[1][3]);
+
+        assert!(matches!(handler.handle_tournament_command(&["1", "2", "0"]), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_tournament_command(&["1", "9", "2"]), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_tournament_command(&["1", "2"]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_override_depth() {
+        let mut handler = CommandHandler::new();
+        handler.override_depth(Some("2"), "4").unwrap();
+        assert_eq!(handler.configs[1].max_search_depth(), 4);
+        assert_eq!(handler.configs[0].max_search_depth(), 2);
+
+        handler.override_depth(None, "6").unwrap();
+        assert!(handler.configs.iter().all(|config| config.max_search_depth() == 6));
+
+        assert!(matches!(handler.override_depth(None, "1"), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.override_depth(Some("9"), "4"), Err(ParseError::OutOfRange(_))));
+
+        handler.override_depth(None, "2").unwrap();
+        // the shallowest depth still plays a move, scored by its child's static heuristic, and the four symmetric
+        // openings tie so the lowest index wins
+        assert!(handler.handle_best_command(&["3"]).unwrap().starts_with("tile Disc: d3,"));
+    }
+
+    #[test]
+    fn test_moves_for_color() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.handle_moves(&["--for", "B"]).unwrap(), handler.handle_moves(&[]).unwrap());
+        assert_eq!(handler.handle_moves(&["--for", "W"]).unwrap(), "moves f4 d6 c5 e3 ");
+
+        // black's only disc is flanked against the edge, so black has nothing to generate while white does
+        let forced_pass = "2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/W";
+        assert_eq!(handler.handle_moves(&["--for", "B", forced_pass]).unwrap(), "moves ");
+        assert_eq!(handler.handle_moves(&["--for", "W", forced_pass]).unwrap(), "moves d1 ");
+
+        assert!(matches!(handler.handle_moves(&["--for", "E"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_moves(&["--for"]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_raw() {
+        let handler = CommandHandler::new();
+        let notation = "4EB3E/3EBWB2E/WE5BE/E2W3B2E/2E2WB3E/E2WBW3E/3EBEW2E/2EBEW3E/B";
+        assert_eq!(handler.handle_raw(&[notation]).unwrap(), "raw 1495472766589663741892773636151968256");
+        assert_eq!(handler.handle_raw(&[]).unwrap(), format!("raw {}", OthelloBoard::new().raw()));
+        assert!(handler.handle_raw(&["8E"]).is_err());
+    }
+
+    #[test]
+    fn test_moves_with_flips() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.handle_moves(&["--flips"]).unwrap(), "moves c4:1 e6:1 f5:1 d3:1 ");
+
+        // a row of two white discs capped by black gives a move that flips both
+        let board = "8E/8E/8E/3E2B3E/3E2B3E/8E/8E/E2WB4E/B";
+        assert_eq!(handler.handle_moves(&["--flips", board]).unwrap(), "moves a8:2 ");
+
+        // a8 flanks along both the row and the column, but is still listed once
+        let board = "8E/8E/8E/3E2B3E/3E2B3E/B7E/W7E/EW6B/B";
+        assert_eq!(handler.handle_moves(&["--flips", board]).unwrap(), "moves a8:2 ");
+        assert_eq!(handler.handle_moves(&[board]).unwrap(), "moves a8 ");
+
+        // the flags can come in either order
+        assert_eq!(handler.handle_moves(&["--flips", "--for", "W"]).unwrap(), "moves f4:1 d6:1 c5:1 e3:1 ");
+        assert_eq!(handler.handle_moves(&["--for", "W", "--flips"]).unwrap(), "moves f4:1 d6:1 c5:1 e3:1 ");
+    }
+
+    #[test]
+    fn test_check() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.handle_check(&["d3", "e2", "f5"]).unwrap(), "d3=ok e2=bad f5=ok");
+
+        // white to move on the same discs has the other four moves
+        let board = "8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/W";
+        assert_eq!(handler.handle_check(&[board, "d3", "e3"]).unwrap(), "d3=bad e3=ok");
+
+        assert!(matches!(handler.handle_check(&[board]), Err(ParseError::MissingArgs(_))));
+        assert!(matches!(handler.handle_check(&["z9"]), Err(ParseError::InvalidTile(_))));
+    }
+
+    #[test]
+    fn test_phase() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.handle_phase(&[]).unwrap(), "phase opening empty 60 moves 0");
+        assert_eq!(handler.handle_phase(&["3W5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B"]).unwrap(), "phase opening empty 57 moves 3");
+        assert_eq!(handler.handle_phase(&["EW6B/8B/8B/8B/8B/8B/8B/8B/B"]).unwrap(), "phase endgame empty 1 moves 59");
+    }
+
+    #[test]
+    fn test_profile_flags() {
+        let mut handler = CommandHandler::new();
+        handler.handle_best_command(&["2"]).unwrap();
+
+        assert_eq!(handler.handle_profile(&["log", "2"]).unwrap(), "Logged runs data to stderr");
+        assert_eq!(handler.handle_profile(&["dump", "2"]).unwrap(), "Dumped cache data to stderr");
+        let export = handler.handle_profile(&["export", "2"]).unwrap();
+        assert_eq!(export.lines().count(), 2);
+        assert!(export.starts_with("max_depth,hits,misses,nodes,evals,time_taken\n3,"));
+        assert_eq!(handler.handle_profile(&["drop", "2"]).unwrap(), "Dropped agent the Level 2");
+        // a dropped agent starts over with no runs
+        assert_eq!(handler.handle_profile(&["export", "2"]).unwrap().lines().count(), 1);
+
+        assert!(matches!(handler.handle_profile(&["clear", "2"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_profile(&["clear", "99"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_profile(&["2", "log"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_profile(&["log"]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_book() {
+        let mut handler = CommandHandler::new();
+        assert_eq!(handler.handle_book(&["export"]).unwrap(), "");
+
+        assert_eq!(handler.handle_book(&["add", "d3"]).unwrap(), "Added d3 to the book, 1 positions");
+        handler.handle_book(&["add", "d3"]).unwrap();
+        // the start position mirrored across the antidiagonal is itself, so f5 shares its entry as a separate move
+        handler.handle_book(&["add", "f5"]).unwrap();
+        handler.handle_book(&["add", "c3", "8E/8E/3EB4E/3E2B3E/3EBW3E/8E/8E/8E/W"]).unwrap();
+        let export = handler.handle_book(&["export"]).unwrap();
+        assert_eq!(export.lines().count(), 2);
+        assert!(export.lines().any(|line| line.ends_with(" d3=2 f5=1")));
+
+        assert!(matches!(handler.handle_book(&["add", "a1"]), Err(ParseError::IllegalMove(_))));
+        assert!(matches!(handler.handle_book(&["add"]), Err(ParseError::MissingArgs(_))));
+        assert!(matches!(handler.handle_book(&["remove"]), Err(ParseError::InvalidArg(_))));
+    }
+
+    #[test]
+    fn test_compare() {
+        let handler = CommandHandler::new();
+        let start = "8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B";
+        assert_eq!(handler.handle_compare(&[start, start]).unwrap(), "same");
+
+        // d3 places a disc on d3 and flips d4, then hands the turn to white
+        let after = "8E/8E/3EB4E/3E2B3E/3EBW3E/8E/8E/8E/W";
+        assert_eq!(handler.handle_compare(&[start, after]).unwrap(), "diff d3 E B\ndiff d4 W B\ndiff turn B W");
+        assert!(matches!(handler.handle_compare(&[start]), Err(ParseError::MissingArgs(_))));
+        assert!(matches!(handler.handle_compare(&[start, "8E"]), Err(ParseError::InvalidNotation(_))));
+    }
+
+    #[test]
+    fn test_must_pass() {
+        let handler = CommandHandler::new();
+        assert_eq!(handler.handle_must_pass(&[]).unwrap(), "no");
+
+        // black's only disc is flanked against the edge, so black has no moves while white can still take it
+        let forced_pass = "2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B";
+        assert_eq!(handler.handle_must_pass(&[forced_pass]).unwrap(), "yes");
+        assert_eq!(handler.handle_must_pass(&["2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/W"]).unwrap(), "no");
+
+        // nobody has a move once the game is over
+        assert_eq!(handler.handle_must_pass(&["3W5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B"]).unwrap(), "no");
+    }
+
+    #[test]
+    fn test_random_move() {
+        let mut handler = CommandHandler::new();
+        handler.set_seed("7").unwrap();
+        let mut other_handler = CommandHandler::new();
+        other_handler.set_seed("7").unwrap();
+
+        // the same seed plays the same game, and every move is legal for the board it was played on
+        for _ in 0..10 {
+            let board = handler.current_board;
+            let response = handler.handle_random_move(&[]).unwrap();
+            assert_eq!(response, other_handler.handle_random_move(&[]).unwrap());
+            if response == "pass" {
+                handler.current_board = board.make_pass();
+                other_handler.current_board = board.make_pass();
+                continue;
+            }
+            let parts = response.split(' ').collect::<Vec<&str>>();
+            assert_eq!(parts[0], "randmove");
+            assert_eq!(board.try_move(parts[1]).unwrap(), handler.current_board);
+            assert_eq!(parts[2], handler.current_board.to_notation());
+        }
+
+        // a board argument is played on without touching the current board
+        let current_board = handler.current_board;
+        assert!(handler.handle_random_move(&["8E/8E/8E/3EWB3E/3EBW3E/8E/8E/8E/B"]).unwrap().starts_with("randmove "));
+        assert_eq!(handler.current_board, current_board);
+
+        assert_eq!(handler.handle_random_move(&["2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B"]).unwrap(), "pass");
+        assert_eq!(handler.handle_random_move(&["3W5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B"]).unwrap(), "notile");
+        assert!(matches!(handler.set_seed("-1"), Err(ParseError::NotANumber(_))));
+    }
+
+    #[test]
+    fn test_coords() {
+        let mut handler = CommandHandler::new();
+        handler.handle_coords(&["numeric"]).unwrap();
+        assert_eq!(handler.handle_moves(&[]).unwrap(), "moves 3,2 5,4 4,5 2,3 ");
+        assert!(handler.handle_move(&["d3"]).is_err());
+        handler.handle_move(&["2,3"]).unwrap();
+
+        handler.handle_coords(&["rowfirst"]).unwrap();
+        assert!(handler.handle_move(&["3c"]).unwrap().starts_with("tile "));
+        assert!(matches!(handler.handle_coords(&["columnfirst"]), Err(ParseError::InvalidArg(_))));
+    }
+
+    #[test]
+    fn test_units() {
+        let mut handler = CommandHandler::new();
+        // a position two tiles from the end, where scores are counted in discs
+        let board = "B5W2E/8B/8B/8B/8B/8B/8B/8W/B";
+        let raw = handler.handle_best_command(&["2", board]).unwrap();
+        assert!(!raw.contains("Centidiscs"));
+
+        handler.handle_units(&["centidisc"]).unwrap();
+        let board_value = OthelloBoard::from_notation(board).unwrap();
+        let best = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1])).find_best_move(&board_value).unwrap();
+        let centi_discs = eval::to_centi_discs(best.heuristic, &board_value.make_move(best.tile)).unwrap();
+        assert_eq!(handler.handle_best_command(&["2", board]).unwrap(), format!("{}, Centidiscs: {}", raw, centi_discs));
+        // every move of the endgame board is scored in discs, so each pair carries its centi-discs
+        for response in [handler.handle_best_moves_command(&["2", "2", board]), handler.handle_ranked_command(&["2", board])] {
+            for pair in response.unwrap().split_whitespace().skip(1) {
+                let (tile, scores) = pair.split_once('=').unwrap();
+                let (heuristic, centi_discs) = scores.split_once('/').unwrap();
+                let child = board_value.make_move(CoordStyle::Algebraic.parse(tile).unwrap());
+                let expected = eval::to_centi_discs(heuristic.parse().unwrap(), &child).unwrap();
+                assert_eq!(centi_discs, expected.to_string());
+            }
+        }
+
+        // the positional heuristic of the opening counts no discs, so the field is left out rather than made up
+        let opening = handler.handle_best_command(&["3"]).unwrap();
+        assert!(opening.starts_with("tile ") && !opening.contains("Centidiscs"));
+        let ranked = handler.handle_ranked_command(&["3"]).unwrap();
+        assert!(ranked.split_whitespace().skip(1).all(|pair| pair.contains('=') && !pair.contains('/')));
+
+        handler.handle_units(&["raw"]).unwrap();
+        assert_eq!(handler.handle_best_command(&["2", board]).unwrap(), raw);
+        assert!(matches!(handler.handle_units(&["centipawn"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_units(&[]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_config_contempt() {
+        let mut handler = CommandHandler::new();
+        handler.handle_config(&["3", "contempt", "-2.5"]).unwrap();
+        assert_eq!(handler.configs[2].contempt(), -2.5f32);
+        assert!(matches!(handler.handle_config(&["3", "contempt", "inf"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_config(&["3", "spite", "1"]), Err(ParseError::InvalidArg(_))));
+    }
+
+    #[test]
+    fn test_config_scoring() {
+        let mut handler = CommandHandler::new();
+        handler.handle_config(&["2", "scoring", "empties"]).unwrap();
+        assert_eq!(handler.configs[1].scoring(), ScoringMode::EmptiesToWinner);
+        handler.handle_config(&["2", "scoring", "strict"]).unwrap();
+        assert_eq!(handler.configs[1].scoring(), ScoringMode::Strict);
+        assert!(matches!(handler.handle_config(&["2", "scoring", "tournament"]), Err(ParseError::InvalidArg(_))));
+    }
+
+    #[test]
+    fn test_echo_board() {
+        let mut handler = CommandHandler::new();
+        assert_eq!(handler.handle_move(&["d3"]).unwrap().lines().count(), 1);
+
+        handler.handle_echo(&["board", "on"]).unwrap();
+        let response = handler.handle_move(&["c5"]).unwrap();
+        let board = handler.current_board;
+        assert_eq!(response, format!("tile {}\n{:#}", board.to_notation(), board));
+        assert!(handler.handle_reset(&[]).unwrap().contains('●'));
+        assert!(handler.handle_flip().contains('●'));
+
+        handler.handle_echo(&["board", "off"]).unwrap();
+        assert_eq!(handler.handle_reset(&[]).unwrap().lines().count(), 1);
+        assert!(matches!(handler.handle_echo(&["moves", "on"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_echo(&["board", "yes"]), Err(ParseError::InvalidArg(_))));
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let handler = CommandHandler::new();
+        let divide = handler.handle_perft_divide(&["3"]).unwrap();
+        let lines = divide.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[..4].iter().all(|line| line.ends_with(" 14")));
+        assert_eq!(lines[4], "divide total 56");
+
+        let forced_pass = "2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B";
+        assert!(handler.handle_perft_divide(&["1", forced_pass]).unwrap().starts_with("divide pass 1\n"));
+        let total = format!("divide total {}", OthelloBoard::from_notation(forced_pass).unwrap().perft(4));
+        assert!(handler.handle_perft_divide(&["4", forced_pass]).unwrap().ends_with(&total));
+        assert_eq!(handler.handle_perft_divide(&["2", "8B/8B/8B/8B/8B/8B/8B/8B/W"]).unwrap(), "divide total 1");
+        assert!(matches!(handler.handle_perft_divide(&["0"]), Err(ParseError::OutOfRange(_))));
+    }
+
+    #[test]
+    fn test_both_sides() {
+        let mut handler = CommandHandler::new();
+        let response = handler.handle_both_sides_command(&["3"]).unwrap();
+        let lines = response.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("black tile ") && lines[1].starts_with("white tile "));
+
+        // black's only disc is stuck against the edge, so black must pass while white can move
+        let response = handler.handle_both_sides_command(&["3", "2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/W"]).unwrap();
+        assert!(response.starts_with("black pass\nwhite tile "));
+    }
+
+    #[test]
+    fn test_flips() {
+        let mut handler = CommandHandler::new();
+        assert_eq!(handler.handle_flips(&["d3"]).unwrap(), "flips d4 ");
+        assert!(matches!(handler.handle_flips(&["a1"]), Err(ParseError::IllegalMove(_))));
+        assert!(matches!(handler.handle_flips(&[]), Err(ParseError::MissingArgs(_))));
+
+        handler.handle_coords(&["numeric"]).unwrap();
+        assert_eq!(handler.handle_flips(&["2,3"]).unwrap(), "flips 3,3 ");
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut handler = CommandHandler::new();
+        let response = handler.handle_trace_command(&["2", "d3", "c5", "f6"]).unwrap();
+        let pairs = response.split_whitespace().skip(1).collect::<Vec<&str>>();
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs[0].starts_with("d3=") && pairs[2].starts_with("f6="));
+
+        // each score matches searching the position after the move on its own
+        let board = OthelloBoard::new().make_move(Tile::new(2, 3));
+        let score = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1])).evaluate_position(&board);
+        assert_eq!(pairs[0], format!("d3={}", score));
+        assert!(handler.current_board == OthelloBoard::new());
+
+        match handler.handle_trace_command(&["2", "d3", "a1"]) {
+            Err(ParseError::IllegalMove(msg)) => assert_eq!(msg, "Move a1 at 2 is not legal"),
+            _ => panic!("Expected an illegal move error"),
+        }
+        assert!(matches!(handler.handle_trace_command(&["2"]), Err(ParseError::MissingArgs(_))));
+
+        // black has no moves here, so black passes and white's move is traced
+        let forced_pass = OthelloBoard::from_notation("2WB5E/8E/8E/3E2W3E/3E2W3E/8E/8E/8E/B").unwrap();
+        handler.current_board = forced_pass;
+        let board = forced_pass.make_pass().make_move(Tile::new(0, 3));
+        let score = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1])).evaluate_position(&board);
+        assert_eq!(handler.handle_trace_command(&["2", "d1"]).unwrap(), format!("trace d1={} ", score));
+        assert!(handler.current_board == forced_pass);
+    }
+
+    #[test]
+    fn test_at_ply() {
+        let mut handler = CommandHandler::new();
+        let transcript = "f5d6c3d3c4f4";
+        let board = OthelloBoard::new().try_move("f5").unwrap().try_move("d6").unwrap().try_move("c3").unwrap();
+        let best = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[1])).find_best_move(&board).unwrap();
+        assert_eq!(handler.handle_at_ply_command(&[transcript, "3", "2"]).unwrap(), format!("tile {}", best));
+        assert_eq!(handler.current_board, OthelloBoard::new());
+        assert!(handler.handle_at_ply_command(&[transcript, "0", "2"]).unwrap().starts_with("tile "));
+        assert!(handler.handle_at_ply_command(&[transcript, "6", "2"]).unwrap().starts_with("tile "));
+
+        assert!(matches!(handler.handle_at_ply_command(&[transcript, "7", "2"]), Err(ParseError::OutOfRange(_))));
+        let err = handler.handle_at_ply_command(&["f5a1", "2", "2"]).unwrap_err();
+        assert_eq!(err, ParseError::IllegalMove("Move a1 at 2 is not legal".into()));
+        assert!(matches!(handler.handle_at_ply_command(&[transcript, "x", "2"]), Err(ParseError::NotANumber(_))));
+        assert!(matches!(handler.handle_at_ply_command(&[transcript, "3"]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_check_symmetry() {
+        let mut handler = CommandHandler::new();
+        handler.handle_config(&["4", "cache", "off"]).unwrap();
+        let board = "2EW3B2W/6B2W/EB3W2BW/BWB2W3B/E2W5B/2W4BWB/7WB/3EB3WB/W";
+        let score = OthelloAgent::new(AgentConfig::new(LEVEL_DEPTHS[3]).with_cache(false))
+            .evaluate_position(&OthelloBoard::from_notation(board).unwrap());
+        assert_eq!(handler.handle_check_symmetry_command(&["4", board]).unwrap(), format!("checksym ok {}", score));
+        assert!(handler.handle_check_symmetry_command(&["4"]).unwrap().starts_with("checksym ok "));
+        // cached scores are stored with the bound they proved, so a cached search agrees across symmetries too
+        let mut cached_handler = CommandHandler::new();
+        assert_eq!(cached_handler.handle_check_symmetry_command(&["4", board]).unwrap(), format!("checksym ok {}", score));
+        assert!(matches!(handler.handle_check_symmetry_command(&[]), Err(ParseError::MissingArgs(_))));
+    }
+
+    #[test]
+    fn test_warm() {
+        let mut handler = CommandHandler::new();
+        let response = handler.handle_warm_command(&["4", "3"]).unwrap();
+        assert!(response.starts_with("Warmed agent Level 4 to depth 3 with "));
+        assert!(handler.get_agent(4).cache.stats().fill_factor > 0.0);
+
+        assert!(matches!(handler.handle_warm_command(&["4"]), Err(ParseError::MissingArgs(_))));
+        assert!(matches!(handler.handle_warm_command(&["4", "deep"]), Err(ParseError::NotANumber(_))));
+    }
+
+    #[test]
+    fn test_add_level() {
+        let mut handler = CommandHandler::new();
+        let new_level = (MAX_LEVEL + 1).to_string();
+
+        handler.handle_add_level(&["4"]).unwrap();
+        handler.handle_add_level(&["20", "100"]).unwrap();
+        assert_eq!(handler.configs[MAX_LEVEL + 1].time_limit(), Some(Duration::from_millis(100)));
+        assert_eq!(handler.parse_level(&new_level).unwrap(), MAX_LEVEL + 1);
+        assert!(handler.handle_best_command(&[&new_level]).unwrap().starts_with("tile "));
+
+        assert!(matches!(handler.handle_add_level(&["1"]), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.handle_add_level(&["deep"]), Err(ParseError::NotANumber(_))));
+        assert_eq!(handler.configs.len(), MAX_LEVEL + 2);
+    }
+
+    #[test]
+    fn test_panic_recovery() {
+        let mut handler = CommandHandler::new();
+        handler.handle_command("best 1").unwrap();
+        assert!(handler.get_optional_agent(1).is_some());
+
+        assert_eq!(handler.catch_command("panic now"), Err(String::from("deliberate panic at now")));
+        assert!(handler.get_optional_agent(1).is_some());
+        assert_eq!(handler.handle_line("panic"), LineStatus::Error);
+
+        // the handler keeps serving commands after the panics
+        assert_eq!(handler.handle_line("best 1"), LineStatus::Ok);
+        assert!(matches!(handler.catch_command("moves"), Ok(Ok(_))));
+        assert!(matches!(handler.catch_command("nothing"), Ok(Err(ParseError::UnknownCommand(_)))));
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        let mut handler = CommandHandler::new();
+        let notation = format!("8E/8E/8E/3EWB3E/3EBW3E/8E/8E/{}/B", "E".repeat(DEFAULT_MAX_LINE_LENGTH));
+        assert!(matches!(handler.handle_command(&format!("count {}", notation)), Err(ParseError::OutOfRange(_))));
+        assert_eq!(handler.handle_line(&format!("count {}", notation)), LineStatus::Error);
+
+        // the limit is on the whole line, so a line exactly at it is still parsed
+        handler.set_max_line_length("12").unwrap();
+        assert_eq!(handler.max_line_length(), 12);
+        assert_eq!(handler.handle_command("moves --for").unwrap_err().kind(), "missing_args");
+        assert!(matches!(handler.handle_command("moves --for B"), Err(ParseError::OutOfRange(_))));
+
+        assert!(matches!(handler.set_max_line_length("0"), Err(ParseError::OutOfRange(_))));
+        assert!(matches!(handler.set_max_line_length("long"), Err(ParseError::NotANumber(_))));
+        assert_eq!(handler.max_line_length(), 12);
+    }
+
+    #[test]
+    fn test_hashing() {
+        let mut handler = CommandHandler::new();
+        assert!(handler.handle_info().contains("info hashing perlevel\n"));
+        let per_level = (handler.handle_hash(&["1"]).unwrap(), handler.handle_hash(&["2"]).unwrap());
+        assert_ne!(per_level.0, per_level.1);
+
+        // a shared hasher gives the same key at every level, and the same keys again from the same seed
+        assert_eq!(handler.handle_hashing(&["shared", "7"]).unwrap(), "Set hashing to shared");
+        assert!(handler.get_optional_agent(1).is_none());
+        let shared = handler.handle_hash(&["1"]).unwrap();
+        assert_eq!(handler.handle_hash(&["2"]).unwrap(), shared);
+        assert!(handler.handle_info().contains("info hashing shared\n"));
+        handler.handle_hashing(&["shared", "7"]).unwrap();
+        assert_eq!(handler.handle_hash(&["3"]).unwrap(), shared);
+
+        handler.handle_hashing(&["shared"]).unwrap();
+        assert_eq!(handler.handle_hash(&["1"]).unwrap(), handler.handle_hash(&["2"]).unwrap());
+
+        handler.handle_hashing(&["perlevel"]).unwrap();
+        assert_ne!(handler.handle_hash(&["1"]).unwrap(), handler.handle_hash(&["2"]).unwrap());
+
+        assert!(matches!(handler.handle_hashing(&[]), Err(ParseError::MissingArgs(_))));
+        assert!(matches!(handler.handle_hashing(&["global"]), Err(ParseError::InvalidArg(_))));
+        assert!(matches!(handler.handle_hashing(&["shared", "-1"]), Err(ParseError::NotANumber(_))));
+    }
+}
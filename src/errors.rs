@@ -7,27 +7,56 @@ use std::fmt;
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
-#[derive(Debug)]
-pub struct ParseError {
-    details: String,
+// each variant carries the human readable message, the variant itself lets callers tell failures apart
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidTile(String),
+    InvalidNotation(String),
+    IllegalMove(String),
+    UnknownCommand(String),
+    MissingArgs(String),
+    InvalidArg(String),
+    NotANumber(String),
+    OutOfRange(String),
 }
 
 impl ParseError {
-    pub(crate) fn new(msg: &str) -> ParseError {
-        ParseError {
-            details: msg.to_string()
+    // a stable machine readable name for the variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::InvalidTile(_) => "invalid_tile",
+            ParseError::InvalidNotation(_) => "invalid_notation",
+            ParseError::IllegalMove(_) => "illegal_move",
+            ParseError::UnknownCommand(_) => "unknown_command",
+            ParseError::MissingArgs(_) => "missing_args",
+            ParseError::InvalidArg(_) => "invalid_arg",
+            ParseError::NotANumber(_) => "not_a_number",
+            ParseError::OutOfRange(_) => "out_of_range",
+        }
+    }
+
+    fn details(&self) -> &str {
+        match self {
+            ParseError::InvalidTile(details)
+            | ParseError::InvalidNotation(details)
+            | ParseError::IllegalMove(details)
+            | ParseError::UnknownCommand(details)
+            | ParseError::MissingArgs(details)
+            | ParseError::InvalidArg(details)
+            | ParseError::NotANumber(details)
+            | ParseError::OutOfRange(details) => details
         }
     }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
+        write!(f, "{}", self.details())
     }
 }
 
 impl Error for ParseError {
     fn description(&self) -> &str {
-        &self.details
+        self.details()
     }
-}
\ No newline at end of file
+}
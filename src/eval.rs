@@ -2,9 +2,108 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
-use crate::board::{BLACK, OthelloBoard, WHITE};
+use std::fmt;
+use crate::board::{Color, OthelloBoard};
+use crate::logger;
 use crate::tile::{Tile, TILES};
 
+// once this few tiles are empty only the final disc count matters, so the search stops using the positional heuristic
+pub const ENDGAME_EMPTY_COUNT: usize = 10;
+// while more than this many tiles are empty the game is still in the opening
+pub const OPENING_EMPTY_COUNT: usize = 44;
+// once this few tiles are empty the few remaining moves make mobility noisy, so its weight tapers off from here down to
+// nothing at ENDGAME_EMPTY_COUNT, where the leaves switch to the disc difference anyway
+pub const MOBILITY_TAPER_EMPTY_COUNT: usize = 20;
+// scores for finished games, every heuristic lies strictly between them and they also bound the search window
+// they are kept small enough that a win by more discs still scores higher than a win by fewer in f32
+pub const WIN: f32 = 1e6;
+pub const LOSS: f32 = -1e6;
+const MAX_DISC_DIFFERENCE: f32 = 64f32;
+
+// the stage of the game by how many tiles are left empty, the endgame is where the leaves switch to disc counts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Midgame,
+    Endgame,
+}
+
+impl Phase {
+    pub fn from_board(board: &OthelloBoard) -> Self {
+        let empty_count = board.count_tiles(Color::Empty);
+        if empty_count <= ENDGAME_EMPTY_COUNT {
+            Phase::Endgame
+        } else if empty_count > OPENING_EMPTY_COUNT {
+            Phase::Opening
+        } else {
+            Phase::Midgame
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Opening => "opening",
+            Phase::Midgame => "midgame",
+            Phase::Endgame => "endgame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// how a finished game is counted, strict counts only the discs on the board while empties to winner follows
+// tournament rules, where the tiles left empty by a game that ends early go to the side with more discs
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScoringMode {
+    #[default]
+    Strict,
+    EmptiesToWinner,
+}
+
+// scores a position from black's perspective, the search can be given one in place of find_heuristic
+pub type EvalFn = fn(&OthelloBoard) -> f32;
+
+#[derive(Copy, Clone)]
+pub struct EvalWeights {
+    pub parity: f32,
+    // used in place of the parity weight during the opening, negative since holding fewer discs keeps more moves open
+    pub opening_parity: f32,
+    pub corner: f32,
+    pub corner_grab: f32,
+    pub mobility: f32,
+    pub potential_mobility: f32,
+    pub xc_square: f32,
+    pub edge: f32,
+    pub stability: f32,
+    pub interior: f32,
+}
+
+pub const WEIGHTS: EvalWeights = EvalWeights {
+    parity: 50f32,
+    opening_parity: -50f32,
+    corner: 100f32,
+    corner_grab: 75f32,
+    mobility: 100f32,
+    potential_mobility: 50f32,
+    xc_square: 50f32,
+    edge: 50f32,
+    stability: 100f32,
+    interior: 25f32,
+};
+
+impl fmt::Display for EvalWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parity={} opening_parity={} corner={} corner_grab={} mobility={} potential_mobility={} xc_square={} edge={} \
+            stability={} interior={}",
+            self.parity, self.opening_parity, self.corner, self.corner_grab, self.mobility,
+            self.potential_mobility, self.xc_square, self.edge, self.stability, self.interior
+        )
+    }
+}
+
 const CORNERS: [[i8; 2]; 4] = [[0, 0], [0, 7], [7, 0], [7, 7]];
 const XC_SQUARES: [[i8; 2]; 12] = [
     [1, 1], [1, 6], [6, 1], [6, 6], [0, 1], [0, 6],
@@ -12,18 +111,14 @@ const XC_SQUARES: [[i8; 2]; 12] = [
 ];
 
 fn find_parity_heuristic(board: &OthelloBoard) -> f32 {
-    let mut white_score = 0f32;
-    let mut black_score = 0f32;
-    for tile in TILES {
-        let color = board.get_tile(tile);
-        if color == WHITE {
-            white_score += 1f32;
-        }
-        if color == BLACK {
-            black_score += 1f32;
-        }
+    let (black_count, white_count) = board.disc_counts();
+    let black_score = black_count as f32;
+    let white_score = white_count as f32;
+    if black_score + white_score != 0f32 {
+        (black_score - white_score) / (black_score + white_score)
+    } else {
+        0f32
     }
-    (black_score - white_score) / (black_score + white_score)
 }
 
 fn find_corner_heuristic(board: &OthelloBoard) -> f32 {
@@ -31,10 +126,10 @@ fn find_corner_heuristic(board: &OthelloBoard) -> f32 {
     let mut black_corners = 0f32;
     for corner in CORNERS {
         let color = board.get_tile(Tile::new(corner[0], corner[1]));
-        if color == WHITE {
+        if color == Color::White {
             white_corners += 1f32;
         }
-        if color == BLACK {
+        if color == Color::Black {
             black_corners += 1f32;
         }
     }
@@ -45,15 +140,41 @@ fn find_corner_heuristic(board: &OthelloBoard) -> f32 {
     }
 }
 
+fn count_corner_moves(board: &OthelloBoard, color: Color) -> f32 {
+    // a corner can be flanked from several directions so collect them as a mask to avoid double counting
+    let mut corner_mask = 0u8;
+    board.find_potential_moves(color, |mov| {
+        if mov.is_corner() {
+            corner_mask |= 1 << ((mov.row / 7) * 2 + mov.col / 7);
+        }
+    });
+    corner_mask.count_ones() as f32
+}
+
+fn find_corner_grab_heuristic(board: &OthelloBoard) -> f32 {
+    let white_grabs = count_corner_moves(board, Color::White);
+    let black_grabs = count_corner_moves(board, Color::Black);
+    if black_grabs + white_grabs != 0f32 {
+        (black_grabs - white_grabs) / (black_grabs + white_grabs)
+    } else {
+        0f32
+    }
+}
+
 fn find_xc_square_heuristic(board: &OthelloBoard) -> f32 {
     let mut white_squares = 0f32;
     let mut black_squares = 0f32;
     for square in XC_SQUARES {
+        // once the adjacent corner is taken the square can no longer give it away, so it is only penalized while empty
+        let corner = Tile::new(if square[0] < 4 { 0 } else { 7 }, if square[1] < 4 { 0 } else { 7 });
+        if board.get_tile(corner) != Color::Empty {
+            continue;
+        }
         let color = board.get_tile(Tile::new(square[0], square[1]));
-        if color == WHITE {
+        if color == Color::White {
             white_squares += 1f32;
         }
-        if color == BLACK {
+        if color == Color::Black {
             black_squares += 1f32;
         }
     }
@@ -66,23 +187,603 @@ fn find_xc_square_heuristic(board: &OthelloBoard) -> f32 {
 }
 
 fn find_mobility_heuristic(board: &OthelloBoard) -> f32 {
-    let white_moves = board.count_potential_moves(WHITE) as f32;
-    let black_moves = board.count_potential_moves(BLACK) as f32;
-    if white_moves + black_moves == 0f32 {
+    let white_moves = board.count_potential_moves(Color::White) as f32;
+    let black_moves = board.count_potential_moves(Color::Black) as f32;
+    if white_moves + black_moves != 0f32 {
         (black_moves - white_moves) / (black_moves + white_moves)
     } else {
         0f32
     }
 }
 
-fn find_stability_heuristic(board: &OthelloBoard) -> f32 {
+fn find_parity_weight(board: &OthelloBoard) -> f32 {
+    if Phase::from_board(board) == Phase::Opening {
+        WEIGHTS.opening_parity
+    } else {
+        WEIGHTS.parity
+    }
+}
+
+fn find_mobility_weight(board: &OthelloBoard) -> f32 {
+    let empty_count = board.count_tiles(Color::Empty);
+    if empty_count >= MOBILITY_TAPER_EMPTY_COUNT {
+        WEIGHTS.mobility
+    } else if empty_count <= ENDGAME_EMPTY_COUNT {
+        0f32
+    } else {
+        let taper_range = (MOBILITY_TAPER_EMPTY_COUNT - ENDGAME_EMPTY_COUNT) as f32;
+        WEIGHTS.mobility * (empty_count - ENDGAME_EMPTY_COUNT) as f32 / taper_range
+    }
+}
+
+fn count_potential_mobility(board: &OthelloBoard, color: Color) -> f32 {
+    let opposite_color = color.opposite();
+    let mut count = 0f32;
+    // count each empty tile bordering the opponent, since these are where future moves come from
+    for tile in TILES {
+        if board.get_tile(tile) != Color::Empty {
+            continue;
+        }
+        let borders_opponent = board.neighbors(tile)
+            .into_iter()
+            .any(|neighbor| board.get_tile(neighbor) == opposite_color);
+        if borders_opponent {
+            count += 1f32;
+        }
+    }
+    count
+}
+
+fn find_potential_mobility_heuristic(board: &OthelloBoard) -> f32 {
+    let white_potential = count_potential_mobility(board, Color::White);
+    let black_potential = count_potential_mobility(board, Color::Black);
+    if black_potential + white_potential != 0f32 {
+        (black_potential - white_potential) / (black_potential + white_potential)
+    } else {
+        0f32
+    }
+}
+
+fn find_safe_edge_mask(board: &OthelloBoard, color: Color) -> u64 {
+    let mut safe_mask = 0u64;
+    for corner in CORNERS {
+        let corner = Tile::new(corner[0], corner[1]);
+        if board.get_tile(corner) != color {
+            continue;
+        }
+        // walk along both edges away from the owned corner, every contiguous disc can never be flipped
+        let row_step = if corner.row == 0 { 1 } else { -1 };
+        let col_step = if corner.col == 0 { 1 } else { -1 };
+        for step in [[row_step, 0], [0, col_step]] {
+            let mut next = Some(corner);
+            while let Some(tile) = next {
+                if board.get_tile(tile) != color {
+                    break;
+                }
+                // a mask is used since edges anchored at both ends would otherwise be counted twice
+                safe_mask |= 1 << tile.index();
+                next = tile.offset(step[0], step[1]);
+            }
+        }
+    }
+    safe_mask
+}
+
+fn find_edge_heuristic(board: &OthelloBoard) -> f32 {
+    let white_edges = find_safe_edge_mask(board, Color::White).count_ones() as f32;
+    let black_edges = find_safe_edge_mask(board, Color::Black).count_ones() as f32;
+    if black_edges + white_edges != 0f32 {
+        (black_edges - white_edges) / (black_edges + white_edges)
+    } else {
+        0f32
+    }
+}
+
+fn find_stability_heuristic(_board: &OthelloBoard) -> f32 {
     0f32
 }
 
+fn count_interior_discs(board: &OthelloBoard, color: Color) -> f32 {
+    let mut count = 0f32;
+    // a disc with no empty tile beside it can't be the first in a line the opponent flanks, so it gives away no moves
+    for tile in TILES {
+        if board.get_tile(tile) != color {
+            continue;
+        }
+        if !board.has_empty_neighbor(tile) {
+            count += 1f32;
+        }
+    }
+    count
+}
+
+fn find_interior_heuristic(board: &OthelloBoard) -> f32 {
+    let white_interior = count_interior_discs(board, Color::White);
+    let black_interior = count_interior_discs(board, Color::Black);
+    if black_interior + white_interior != 0f32 {
+        (black_interior - white_interior) / (black_interior + white_interior)
+    } else {
+        0f32
+    }
+}
+
+// every term scores the position from black's perspective, so swapping the colors of a board must negate the heuristic
 pub fn find_heuristic(board: &OthelloBoard) -> f32 {
-    50f32 * find_parity_heuristic(board)
-        + 100f32 * find_corner_heuristic(board)
-        + 100f32 * find_mobility_heuristic(board)
-        + 50f32 * find_xc_square_heuristic(board)
-        + 100f32 * find_stability_heuristic(board)
-}
\ No newline at end of file
+    let heuristic = find_parity_weight(board) * find_parity_heuristic(board)
+        + WEIGHTS.corner * find_corner_heuristic(board)
+        + WEIGHTS.corner_grab * find_corner_grab_heuristic(board)
+        + find_mobility_weight(board) * find_mobility_heuristic(board)
+        + WEIGHTS.potential_mobility * find_potential_mobility_heuristic(board)
+        + WEIGHTS.xc_square * find_xc_square_heuristic(board)
+        + WEIGHTS.edge * find_edge_heuristic(board)
+        + WEIGHTS.stability * find_stability_heuristic(board)
+        + WEIGHTS.interior * find_interior_heuristic(board);
+    ensure_finite(heuristic)
+}
+
+// a heuristic that isn't finite poisons every comparison the search makes with it, so debug builds stop on one while
+// release builds log it and carry on with an even score
+pub fn ensure_finite(heuristic: f32) -> f32 {
+    debug_assert!(heuristic.is_finite(), "Heuristic must be finite but was {}", heuristic);
+    if heuristic.is_finite() {
+        heuristic
+    } else {
+        logger::error(&format!("Replaced a heuristic that was not finite, {}, with an even score", heuristic));
+        0f32
+    }
+}
+
+// the raw signed disc difference, unlike the parity heuristic this is not normalized so it never saturates
+pub fn find_disc_difference(board: &OthelloBoard) -> f32 {
+    board.count_tiles(Color::Black) as f32 - board.count_tiles(Color::White) as f32
+}
+
+// the disc difference a finished game is reported with under the scoring mode, a draw has no winner to award empties to
+pub fn find_final_disc_difference(board: &OthelloBoard, scoring: ScoringMode) -> f32 {
+    let difference = find_disc_difference(board);
+    let empty_count = board.count_tiles(Color::Empty) as f32;
+    match scoring {
+        ScoringMode::EmptiesToWinner if difference > 0f32 => difference + empty_count,
+        ScoringMode::EmptiesToWinner if difference < 0f32 => difference - empty_count,
+        _ => difference,
+    }
+}
+
+// the score of a finished game, a win beats any unfinished position and is worth more the more discs it is won by
+pub fn find_terminal_score(board: &OthelloBoard) -> f32 {
+    find_terminal_score_with(board, ScoringMode::Strict)
+}
+
+// like find_terminal_score but counting the discs under the scoring mode
+pub fn find_terminal_score_with(board: &OthelloBoard, scoring: ScoringMode) -> f32 {
+    let difference = find_final_disc_difference(board, scoring);
+    if difference > 0f32 {
+        WIN - MAX_DISC_DIFFERENCE + difference
+    } else if difference < 0f32 {
+        LOSS + MAX_DISC_DIFFERENCE + difference
+    } else {
+        0f32
+    }
+}
+
+// whether a score can only come from a finished game, every heuristic lies strictly closer to zero
+pub fn is_decisive_score(score: f32) -> bool {
+    score >= WIN - MAX_DISC_DIFFERENCE || score <= LOSS + MAX_DISC_DIFFERENCE
+}
+
+// converts the score of a board to hundredths of a disc, like centipawns in chess, so it can be compared with other engines
+// a finished game converts from its terminal score back to its final disc difference, and an endgame board is already
+// scored in discs since every leaf below it is, but the positional heuristic before the endgame counts no discs at all
+pub fn to_centi_discs(score: f32, board: &OthelloBoard) -> Option<i32> {
+    let discs = if score >= WIN - MAX_DISC_DIFFERENCE {
+        score - (WIN - MAX_DISC_DIFFERENCE)
+    } else if score <= LOSS + MAX_DISC_DIFFERENCE {
+        score - (LOSS + MAX_DISC_DIFFERENCE)
+    } else if board.count_tiles(Color::Empty) <= ENDGAME_EMPTY_COUNT {
+        score
+    } else {
+        return None
+    };
+    Some((discs * 100f32).round() as i32)
+}
+
+// the heuristic used at the leaves of the search, which switches to the disc difference near the end of the game
+pub fn find_leaf_heuristic(board: &OthelloBoard) -> f32 {
+    find_leaf_heuristic_with(board, find_heuristic, ScoringMode::Strict)
+}
+
+// like find_leaf_heuristic but with another function standing in for the positional heuristic before the endgame,
+// and with finished games counted under the scoring mode
+pub fn find_leaf_heuristic_with(board: &OthelloBoard, eval_fn: EvalFn, scoring: ScoringMode) -> f32 {
+    if Phase::from_board(board) == Phase::Endgame {
+        if board.is_game_over() {
+            return find_terminal_score_with(board, scoring);
+        }
+        find_disc_difference(board)
+    } else {
+        ensure_finite(eval_fn(board))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use crate::agent::{AgentConfig, OthelloAgent};
+    use crate::board::{Axis, Color, OthelloBoard};
+    use crate::eval::{
+        count_interior_discs, find_corner_grab_heuristic, find_corner_heuristic, find_disc_difference,
+        ensure_finite, find_edge_heuristic, find_final_disc_difference, find_heuristic, find_interior_heuristic, find_leaf_heuristic,
+        find_mobility_heuristic, find_mobility_weight, find_parity_heuristic, find_parity_weight,
+        find_potential_mobility_heuristic, find_safe_edge_mask, find_stability_heuristic, find_terminal_score,
+        find_terminal_score_with, find_xc_square_heuristic, is_decisive_score, to_centi_discs, Phase, ScoringMode,
+        ENDGAME_EMPTY_COUNT, LOSS, MOBILITY_TAPER_EMPTY_COUNT, WEIGHTS, WIN
+    };
+    use crate::tile::{Tile, TILES};
+
+    proptest! {
+        #[test]
+        fn test_heuristic_symmetries(choices in prop::collection::vec(any::<usize>(), 0..64)) {
+            let mut board = OthelloBoard::new();
+            for choice in choices {
+                if board.is_game_over() {
+                    break;
+                }
+                let moves = board.find_current_moves_as_vec();
+                board = if moves.is_empty() { board.make_pass() } else { board.make_move(moves[choice % moves.len()]) };
+            }
+            prop_assert_eq!(find_heuristic(&board), -find_heuristic(&board.color_swapped()));
+            // the board has no preferred orientation, so reflections must score the same
+            for axis in [Axis::Horizontal, Axis::Vertical, Axis::Diagonal, Axis::AntiDiagonal] {
+                prop_assert_eq!(find_heuristic(&board), find_heuristic(&board.mirrored(axis)));
+            }
+        }
+
+        // the terms divide by disc and move counts, so any fill of the board is checked and not only reachable ones
+        #[test]
+        fn test_heuristic_finite(colors in prop::collection::vec(0u8..3, 64), black_move in any::<bool>()) {
+            let mut board = OthelloBoard::empty();
+            board.black_move = black_move;
+            for (tile, color) in TILES.into_iter().zip(colors) {
+                board.set_tile(tile, Color::from_u8(color));
+            }
+            prop_assert!(find_heuristic(&board).is_finite());
+            prop_assert!(find_leaf_heuristic(&board).is_finite());
+        }
+    }
+
+    #[test]
+    fn test_ensure_finite() {
+        assert_eq!(ensure_finite(12.5), 12.5);
+        assert_eq!(ensure_finite(LOSS), LOSS);
+        assert!(find_heuristic(&OthelloBoard::empty()).is_finite());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Heuristic must be finite")]
+    fn test_ensure_finite_nan() {
+        ensure_finite(f32::NAN);
+    }
+
+    #[test]
+    fn test_phase() {
+        assert_eq!(Phase::from_board(&OthelloBoard::new()), Phase::Opening);
+
+        let mut board = OthelloBoard::new();
+        for tile in TILES.into_iter().take(30) {
+            board.set_tile(tile, Color::Black);
+        }
+        assert_eq!(Phase::from_board(&board), Phase::Midgame);
+
+        // the endgame starts exactly where the leaves switch to the disc difference
+        for tile in TILES.into_iter().take(64 - ENDGAME_EMPTY_COUNT) {
+            board.set_tile(tile, Color::White);
+        }
+        // black on a1 can still flank down the a file, so the game is not over
+        board.set_tile(Tile::new(0, 0), Color::Black);
+        assert_eq!(Phase::from_board(&board), Phase::Endgame);
+        assert_eq!(find_leaf_heuristic(&board), find_disc_difference(&board));
+    }
+
+    #[test]
+    fn test_parity_heuristic() {
+        assert_eq!(find_parity_heuristic(&OthelloBoard::new()), 0f32);
+
+        // three black discs against one white disc
+        let board = OthelloBoard::from_notation("8E/8E/8E/3EBB3E/3EBW3E/8E/8E/8E/W").unwrap();
+        assert_eq!(find_parity_heuristic(&board), 0.5f32);
+
+        let board = OthelloBoard::from_notation("8E/8E/8E/3EWW3E/3EWB3E/8E/8E/8E/B").unwrap();
+        assert_eq!(find_parity_heuristic(&board), -0.5f32);
+    }
+
+    #[test]
+    fn test_opening_parity() {
+        // the same three discs against one score as a disadvantage in the opening but an advantage later on
+        let board = OthelloBoard::from_notation("8E/8E/8E/3EBB3E/3EBW3E/8E/8E/8E/W").unwrap();
+        assert_eq!(find_parity_weight(&board) * find_parity_heuristic(&board), -25f32);
+
+        let mut midgame = board;
+        for tile in TILES.into_iter().take(16) {
+            midgame.set_tile(tile, Color::Black);
+        }
+        for tile in TILES.into_iter().skip(48) {
+            midgame.set_tile(tile, Color::White);
+        }
+        assert_eq!(find_parity_weight(&midgame), WEIGHTS.parity);
+
+        // in the opening black holding four discs to one scores worse than holding three to one
+        let greedy = OthelloBoard::from_notation("8E/8E/8E/3EBBB2E/3EBW3E/8E/8E/8E/W").unwrap();
+        let quiet = OthelloBoard::from_notation("8E/8E/8E/3EWB3E/3EBB3E/8E/8E/8E/W").unwrap();
+        assert!(find_parity_weight(&greedy) * find_parity_heuristic(&greedy)
+            < find_parity_weight(&quiet) * find_parity_heuristic(&quiet));
+    }
+
+    #[test]
+    fn test_corner_heuristic() {
+        assert_eq!(find_corner_heuristic(&OthelloBoard::new()), 0f32);
+
+        // black holds a1 and h1 while white holds only a8
+        let board = OthelloBoard::from_notation("B6EB/8E/8E/3EWB3E/3EBW3E/8E/8E/W7E/W").unwrap();
+        assert_eq!(find_corner_heuristic(&board), 1f32 / 3f32);
+
+        let board = OthelloBoard::from_notation("W6EW/8E/8E/3EWB3E/3EBW3E/8E/8E/B7E/B").unwrap();
+        assert_eq!(find_corner_heuristic(&board), -1f32 / 3f32);
+    }
+
+    #[test]
+    fn test_corner_dominant_position() {
+        // owning every corner outweighs the open tiles around them, for whichever side owns them
+        let board = OthelloBoard::from_notation("B6EB/8E/8E/3EWB3E/3EBW3E/8E/8E/B6EB/W").unwrap();
+        assert!(find_heuristic(&board) > 100f32);
+
+        let board = OthelloBoard::from_notation("W6EW/8E/8E/3EWB3E/3EBW3E/8E/8E/W6EW/B").unwrap();
+        assert!(find_heuristic(&board) < -100f32);
+    }
+
+    #[test]
+    fn test_mobility_heuristic() {
+        assert_eq!(find_mobility_heuristic(&OthelloBoard::new()), 0f32);
+
+        // black in the a1 corner can flank both white discs but white can flank neither the corner nor the solid center
+        let board = OthelloBoard::from_notation("BW6E/1EW6E/8E/3E2B3E/3E2B3E/8E/8E/8E/B").unwrap();
+        assert_eq!(find_mobility_heuristic(&board), 1f32);
+        assert_eq!(find_mobility_weight(&board), WEIGHTS.mobility);
+
+        let board = OthelloBoard::from_notation("WB6E/1EB6E/8E/3E2W3E/3E2W3E/8E/8E/8E/W").unwrap();
+        assert_eq!(find_mobility_heuristic(&board), -1f32);
+    }
+
+    #[test]
+    fn test_stability_heuristic() {
+        // stability is not scored yet, so it stays neutral even for a side with stable discs
+        let board = OthelloBoard::from_notation("B6EB/8E/8E/3EWB3E/3EBW3E/8E/8E/B6EB/W").unwrap();
+        assert_eq!(find_stability_heuristic(&board), 0f32);
+        assert_eq!(find_stability_heuristic(&board.color_swapped()), 0f32);
+    }
+
+    #[test]
+    fn test_corner_grab_heuristic() {
+        // black can immediately take the a1 corner by flanking b1
+        let mut board = OthelloBoard::new();
+        board.set_tile(Tile::new(0, 1), Color::White);
+        board.set_tile(Tile::new(0, 2), Color::Black);
+
+        assert_eq!(find_corner_grab_heuristic(&board), 1f32);
+        assert_eq!(find_corner_grab_heuristic(&OthelloBoard::new()), 0f32);
+
+        // swapping the colors of the flank hands the corner to white instead
+        board.set_tile(Tile::new(0, 1), Color::Black);
+        board.set_tile(Tile::new(0, 2), Color::White);
+
+        assert_eq!(find_corner_grab_heuristic(&board), -1f32);
+        assert!(find_heuristic(&board) < find_heuristic(&OthelloBoard::new()));
+    }
+
+    #[test]
+    fn test_xc_square_heuristic() {
+        // black on the b2 x square is penalized while a1 is empty
+        let mut board = OthelloBoard::new();
+        board.set_tile(Tile::new(1, 1), Color::Black);
+        assert_eq!(find_xc_square_heuristic(&board), -1f32);
+
+        // but not once a1 is owned, by either side
+        board.set_tile(Tile::new(0, 0), Color::Black);
+        assert_eq!(find_xc_square_heuristic(&board), 0f32);
+        board.set_tile(Tile::new(0, 0), Color::White);
+        assert_eq!(find_xc_square_heuristic(&board), 0f32);
+
+        // c squares follow their corner too, white on b1 is ignored since a1 is taken but white on g8 is penalized
+        board.set_tile(Tile::new(0, 1), Color::White);
+        board.set_tile(Tile::new(7, 6), Color::White);
+        assert_eq!(find_xc_square_heuristic(&board), 1f32);
+    }
+
+    #[test]
+    fn test_mobility_ignored_near_end() {
+        // black owns the top half and white the bottom half, black can flank down into the empty tiles but white cannot
+        let mut board = OthelloBoard::new();
+        for tile in TILES {
+            board.set_tile(tile, if tile.row < 4 { Color::Black } else { Color::White });
+        }
+        for col in 0..6 {
+            board.set_tile(Tile::new(7, col), Color::Empty);
+        }
+        assert_eq!(find_mobility_heuristic(&board), 1f32);
+
+        let expected = WEIGHTS.parity * find_parity_heuristic(&board)
+            + WEIGHTS.corner * find_corner_heuristic(&board)
+            + WEIGHTS.corner_grab * find_corner_grab_heuristic(&board)
+            + WEIGHTS.potential_mobility * find_potential_mobility_heuristic(&board)
+            + WEIGHTS.xc_square * find_xc_square_heuristic(&board)
+            + WEIGHTS.edge * find_edge_heuristic(&board)
+            + WEIGHTS.stability * find_stability_heuristic(&board)
+            + WEIGHTS.interior * find_interior_heuristic(&board);
+        assert_eq!(find_heuristic(&board), expected);
+
+        // the weight tapers in linearly from the endgame to full weight
+        let mut empty_tiles = TILES.into_iter().rev().filter(|tile| tile.row >= 4);
+        while board.count_tiles(Color::Empty) < ENDGAME_EMPTY_COUNT {
+            board.set_tile(empty_tiles.next().unwrap(), Color::Empty);
+        }
+        assert_eq!(find_mobility_weight(&board), 0f32);
+        while board.count_tiles(Color::Empty) < (ENDGAME_EMPTY_COUNT + MOBILITY_TAPER_EMPTY_COUNT) / 2 {
+            board.set_tile(empty_tiles.next().unwrap(), Color::Empty);
+        }
+        assert_eq!(find_mobility_weight(&board), WEIGHTS.mobility / 2f32);
+        while board.count_tiles(Color::Empty) < MOBILITY_TAPER_EMPTY_COUNT {
+            board.set_tile(empty_tiles.next().unwrap(), Color::Empty);
+        }
+        assert_eq!(find_mobility_weight(&board), WEIGHTS.mobility);
+    }
+
+    // the heuristic as it was before mobility tapered off, with the full weight until the endgame
+    fn untapered_heuristic(board: &OthelloBoard) -> f32 {
+        let full_weight = if board.count_tiles(Color::Empty) > ENDGAME_EMPTY_COUNT { WEIGHTS.mobility } else { 0f32 };
+        find_heuristic(board) + (full_weight - find_mobility_weight(board)) * find_mobility_heuristic(board)
+    }
+
+    #[test]
+    fn test_mobility_taper_reaches_search() {
+        // play a fixed line to a few tiles above the endgame, so the leaves of a shallow search lie within the taper
+        let mut board = OthelloBoard::new();
+        let mut i = 0;
+        while board.count_tiles(Color::Empty) > ENDGAME_EMPTY_COUNT + 6 {
+            let moves = board.find_current_moves_as_vec();
+            board = if moves.is_empty() { board.make_pass() } else { board.make_move(moves[(i * 2) % moves.len()]) };
+            i += 1;
+        }
+        let tapered = OthelloAgent::new(AgentConfig::new(4)).find_best_move(&board).unwrap();
+        let untapered = OthelloAgent::new(AgentConfig::new(4).with_eval_fn(untapered_heuristic)).find_best_move(&board).unwrap();
+        assert_ne!(tapered.heuristic, untapered.heuristic);
+    }
+
+    #[test]
+    fn test_interior_heuristic() {
+        assert_eq!(find_interior_heuristic(&OthelloBoard::new()), 0f32);
+
+        // black holds a solid 4x4 block in the middle ringed by white, so only black has discs with no empty neighbor
+        let mut board = OthelloBoard::empty();
+        for row in 1..7 {
+            for col in 1..7 {
+                let ring = row == 1 || row == 6 || col == 1 || col == 6;
+                board.set_tile(Tile::new(row, col), if ring { Color::White } else { Color::Black });
+            }
+        }
+        assert_eq!(count_interior_discs(&board, Color::Black), 16f32);
+        assert_eq!(count_interior_discs(&board, Color::White), 0f32);
+        assert_eq!(find_interior_heuristic(&board), 1f32);
+
+        // a corner disc only has its three neighbors on the board to fill
+        for tile in [Tile::new(0, 0), Tile::new(0, 1), Tile::new(1, 0)] {
+            board.set_tile(tile, Color::White);
+        }
+        assert_eq!(count_interior_discs(&board, Color::White), 1f32);
+        assert_eq!(find_interior_heuristic(&board), (16f32 - 1f32) / 17f32);
+    }
+
+    #[test]
+    fn test_disc_difference() {
+        assert_eq!(find_disc_difference(&OthelloBoard::new()), 0f32);
+
+        let board = OthelloBoard::new().make_move(Tile::new(2, 3));
+        assert_eq!(find_disc_difference(&board), 3f32);
+    }
+
+    #[test]
+    fn test_terminal_score() {
+        // a full board split down the middle is a draw
+        let mut board = OthelloBoard::new();
+        for tile in TILES {
+            board.set_tile(tile, if tile.col < 4 { Color::Black } else { Color::White });
+        }
+        assert_eq!(find_terminal_score(&board), 0f32);
+
+        board.set_tile(Tile::new(0, 4), Color::Black);
+        let narrow_win = find_terminal_score(&board);
+        board.set_tile(Tile::new(0, 5), Color::Black);
+        let wide_win = find_terminal_score(&board);
+        assert!(narrow_win > find_heuristic(&board) && wide_win > narrow_win && wide_win < WIN);
+
+        for tile in TILES {
+            board.set_tile(tile, Color::White);
+        }
+        assert_eq!(find_terminal_score(&board), LOSS);
+        assert_eq!(find_leaf_heuristic(&board), LOSS);
+        assert!(is_decisive_score(narrow_win) && is_decisive_score(LOSS) && !is_decisive_score(0f32));
+    }
+
+    #[test]
+    fn test_empties_to_winner_scoring() {
+        // black wipes white out with 58 tiles still empty
+        let board = OthelloBoard::from_notation("8E/8E/8E/3E2B3E/3E3B2E/4EB3E/8E/8E/W").unwrap();
+        assert!(board.is_game_over());
+        assert_eq!(find_final_disc_difference(&board, ScoringMode::Strict), 6f32);
+        assert_eq!(find_final_disc_difference(&board, ScoringMode::EmptiesToWinner), 64f32);
+        assert_eq!(find_terminal_score_with(&board, ScoringMode::Strict), find_terminal_score(&board));
+        assert!(find_terminal_score_with(&board, ScoringMode::EmptiesToWinner) > find_terminal_score(&board));
+
+        // the empties go to white when white wins, and to nobody when the discs are even
+        let swapped = board.color_swapped();
+        assert_eq!(find_final_disc_difference(&swapped, ScoringMode::EmptiesToWinner), -64f32);
+        assert_eq!(find_terminal_score_with(&swapped, ScoringMode::EmptiesToWinner), LOSS);
+        let mut drawn = OthelloBoard::new();
+        for tile in TILES {
+            drawn.set_tile(tile, if tile.row < 3 { Color::Black } else if tile.row < 6 { Color::White } else { Color::Empty });
+        }
+        assert_eq!(find_final_disc_difference(&drawn, ScoringMode::EmptiesToWinner), 0f32);
+    }
+
+    #[test]
+    fn test_centi_discs() {
+        // the positional heuristic counts no discs, while an endgame board is scored by its disc difference
+        let board = OthelloBoard::new();
+        assert_eq!(to_centi_discs(find_heuristic(&board), &board), None);
+        let endgame = OthelloBoard::from_notation("B5W2E/8B/8B/8B/8B/8B/8B/8W/B").unwrap();
+        assert_eq!(to_centi_discs(0f32, &endgame), Some(0));
+        assert_eq!(to_centi_discs(3f32, &endgame), Some(300));
+        assert_eq!(to_centi_discs(-12.345f32, &endgame), Some(-1235));
+
+        // finished games convert from their terminal score to the discs they were won or lost by, whatever the board
+        let board = OthelloBoard::from_notation("8E/8E/8E/3E2B3E/3E3B2E/4EB3E/8E/8E/W").unwrap();
+        assert_eq!(to_centi_discs(find_terminal_score(&board), &board), Some(600));
+        assert_eq!(to_centi_discs(find_terminal_score(&board.color_swapped()), &board), Some(-600));
+        assert_eq!(to_centi_discs(find_terminal_score_with(&board, ScoringMode::EmptiesToWinner), &board), Some(6400));
+    }
+
+    #[test]
+    fn test_potential_mobility_heuristic() {
+        assert_eq!(find_potential_mobility_heuristic(&OthelloBoard::new()), 0f32);
+
+        // a lone white disc on the edge is bordered by empty tiles black can eventually play into
+        let mut board = OthelloBoard::new();
+        board.set_tile(Tile::new(0, 4), Color::White);
+        assert!(find_potential_mobility_heuristic(&board) > 0f32);
+
+        board.set_tile(Tile::new(0, 4), Color::Black);
+        assert!(find_potential_mobility_heuristic(&board) < 0f32);
+    }
+
+    #[test]
+    fn test_edge_heuristic() {
+        assert_eq!(find_edge_heuristic(&OthelloBoard::new()), 0f32);
+
+        // black owns the full top edge anchored at both corners, white owns an edge disc with no corner behind it
+        let mut board = OthelloBoard::new();
+        for col in 0..8 {
+            board.set_tile(Tile::new(0, col), Color::Black);
+        }
+        board.set_tile(Tile::new(3, 7), Color::White);
+
+        assert_eq!(find_safe_edge_mask(&board, Color::Black).count_ones(), 8);
+        assert_eq!(find_safe_edge_mask(&board, Color::White).count_ones(), 0);
+        assert_eq!(find_edge_heuristic(&board), 1f32);
+
+        // white taking the bottom right corner anchors its disc on the right edge
+        for row in 3..8 {
+            board.set_tile(Tile::new(row, 7), Color::White);
+        }
+        assert_eq!(find_safe_edge_mask(&board, Color::White).count_ones(), 5);
+        assert!(find_edge_heuristic(&board) > 0f32 && find_edge_heuristic(&board) < 1f32);
+    }
+}
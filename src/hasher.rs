@@ -2,17 +2,27 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use crate::board::OthelloBoard;
 use crate::tile::Tile;
 
+#[derive(Clone)]
 pub struct ZHasher {
     table: [[i64; 3]; 64],
 }
 
 impl ZHasher {
     pub fn new() -> Self {
-        let mut generator = rand::thread_rng();
+        Self::from_rng(&mut rand::thread_rng())
+    }
+
+    // a seeded hasher generates the same keys every run, so cache behavior is reproducible
+    pub fn from_seed(seed: u64) -> Self {
+        Self::from_rng(&mut StdRng::seed_from_u64(seed))
+    }
+
+    fn from_rng(generator: &mut impl Rng) -> Self {
         let mut hasher = Self {
             table: [[0; 3]; 64]
         };
@@ -29,8 +39,14 @@ impl ZHasher {
         let mut hash = 0i64;
         for i in 0..self.table.len() {
             let t = Tile::from_index(i);
-            hash = hash ^ self.table[i][board.get_tile(t) as usize];
+            hash ^= self.table[i][board.get_tile(t).to_u8() as usize];
         }
         hash
     }
-}
\ No newline at end of file
+}
+
+impl Default for ZHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
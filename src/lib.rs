@@ -0,0 +1,15 @@
+/*
+ * Copyright (c) Joseph Prichard 2022.
+ */
+
+pub mod board;
+pub mod agent;
+pub mod tile;
+pub mod cache;
+pub mod hasher;
+pub mod eval;
+pub mod profile;
+pub mod command;
+pub mod errors;
+pub mod book;
+pub mod logger;
@@ -0,0 +1,65 @@
+/*
+ * Copyright (c) Joseph Prichard 2022.
+ */
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// how much the engine writes to stderr, each level includes everything written by the levels below it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Quiet = 0,
+    Normal = 1,
+    Verbose = 2,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Normal as u8);
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Quiet,
+        1 => LogLevel::Normal,
+        _ => LogLevel::Verbose,
+    }
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    self::level() >= level
+}
+
+// failures are written even when quiet, since stderr is the only place they can be reported outside of a command
+pub fn error(message: &str) {
+    eprintln!("{}", message);
+}
+
+pub fn info(message: &str) {
+    if enabled(LogLevel::Normal) {
+        eprintln!("{}", message);
+    }
+}
+
+pub fn debug(message: &str) {
+    if enabled(LogLevel::Verbose) {
+        eprintln!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logger::{enabled, level, set_level, LogLevel};
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(level(), LogLevel::Normal);
+        assert!(enabled(LogLevel::Quiet) && enabled(LogLevel::Normal) && !enabled(LogLevel::Verbose));
+
+        set_level(LogLevel::Quiet);
+        assert!(enabled(LogLevel::Quiet) && !enabled(LogLevel::Normal));
+        set_level(LogLevel::Verbose);
+        assert!(enabled(LogLevel::Verbose));
+        set_level(LogLevel::Normal);
+    }
+}
@@ -2,30 +2,198 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
+use std::fs::File;
 use std::io;
-use std::io::BufRead;
-use crate::command::CommandHandler;
-
-mod board;
-mod agent;
-mod tile;
-mod cache;
-mod hasher;
-mod eval;
-mod profile;
-mod command;
-mod errors;
+use std::io::{BufRead, Read};
+use std::process;
+use othello_engine::command::{CommandHandler, LineStatus};
+use othello_engine::logger;
+use othello_engine::logger::LogLevel;
+
+struct Options {
+    script: Option<String>,
+    // keep reading commands from stdin once the script is finished
+    stdin: bool,
+    // stop at the first command in the script that fails
+    strict: bool,
+    // overrides the search depth of the level, or of every level if no level is given
+    depth: Option<String>,
+    level: Option<String>,
+    // bypass the caches of every level
+    no_cache: bool,
+    // seeds the moves of the randmove command
+    seed: Option<String>,
+    // seeds one hasher shared by every level
+    hash_seed: Option<String>,
+    max_line_length: Option<String>,
+    log_level: LogLevel,
+}
+
+fn expect_value(args: &mut impl Iterator<Item=String>, name: &str) -> String {
+    match args.next() {
+        Some(value) => value,
+        None => {
+            logger::error(&format!("{} flag needs a value", name));
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        script: None,
+        stdin: false,
+        strict: false,
+        depth: None,
+        level: None,
+        no_cache: false,
+        seed: None,
+        hash_seed: None,
+        max_line_length: None,
+        log_level: LogLevel::Normal,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--script" => options.script = Some(expect_value(&mut args, "Script")),
+            "--depth" => options.depth = Some(expect_value(&mut args, "Depth")),
+            "--level" => options.level = Some(expect_value(&mut args, "Level")),
+            "--no-cache" => options.no_cache = true,
+            "--seed" => options.seed = Some(expect_value(&mut args, "Seed")),
+            "--hash-seed" => options.hash_seed = Some(expect_value(&mut args, "Hash seed")),
+            "--max-line-length" => options.max_line_length = Some(expect_value(&mut args, "Max line length")),
+            "--stdin" => options.stdin = true,
+            "--strict" => options.strict = true,
+            "--quiet" => options.log_level = LogLevel::Quiet,
+            "--verbose" => options.log_level = LogLevel::Verbose,
+            _ => {
+                logger::error(&format!("Unknown argument {}", arg));
+                process::exit(2);
+            }
+        }
+    }
+    if options.level.is_some() && options.depth.is_none() {
+        logger::error("Level flag must be used with the depth flag");
+        process::exit(2);
+    }
+    // without a script there is nothing to read but stdin
+    if options.script.is_none() {
+        options.stdin = true;
+    }
+    options
+}
+
+// reads lines like BufRead::lines, but never buffers more than one byte past the max length, the rest of a longer
+// line is skipped so the handler still sees that it was too long
+fn bounded_lines(mut reader: impl BufRead, max_length: usize) -> impl Iterator<Item=io::Result<String>> {
+    std::iter::from_fn(move || {
+        let mut buf = Vec::new();
+        match (&mut reader).take(max_length as u64 + 1).read_until(b'\n', &mut buf) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        } else if buf.len() > max_length {
+            if let Err(err) = skip_line(&mut reader) {
+                return Some(Err(err));
+            }
+        }
+        Some(Ok(String::from_utf8_lossy(&buf).into_owned()))
+    })
+}
+
+fn skip_line(reader: &mut impl BufRead) -> io::Result<()> {
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(());
+        }
+        match available.iter().position(|byte| *byte == b'\n') {
+            Some(i) => {
+                reader.consume(i + 1);
+                return Ok(());
+            }
+            None => {
+                let len = available.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+// feeds each line to the handler, returning false once the engine should stop
+fn run_lines(handler: &mut CommandHandler, lines: impl Iterator<Item=io::Result<String>>, strict: bool) -> bool {
+    for line in lines {
+        match line {
+            Ok(line) => match handler.handle_line(&line) {
+                LineStatus::Ok => {}
+                LineStatus::Error if !strict => {}
+                LineStatus::Error => {
+                    logger::error(&format!("Stopped at failed command {}", line));
+                    process::exit(1);
+                }
+                LineStatus::Quit => return false,
+            },
+            Err(err) => {
+                logger::error(&format!("Error occurred while accepting input {}", err))
+            }
+        }
+    }
+    true
+}
 
 pub fn main() {
-    eprintln!("Started the engine");
+    let options = parse_options();
+    logger::set_level(options.log_level);
+    logger::info("Started the engine");
 
     let mut handler = CommandHandler::new();
-    for line in io::stdin().lock().lines() {
-        match line {
-            Ok(line) => handler.handle_line(&line),
+    if options.no_cache {
+        handler.disable_cache();
+    }
+    if let Some(depth) = &options.depth {
+        if let Err(err) = handler.override_depth(options.level.as_deref(), depth) {
+            logger::error(&format!("Invalid depth override: {}", err));
+            process::exit(2);
+        }
+    }
+    if let Some(seed) = &options.seed {
+        if let Err(err) = handler.set_seed(seed) {
+            logger::error(&format!("Invalid seed: {}", err));
+            process::exit(2);
+        }
+    }
+    if let Some(seed) = &options.hash_seed {
+        if let Err(err) = handler.share_hasher(seed) {
+            logger::error(&format!("Invalid hash seed: {}", err));
+            process::exit(2);
+        }
+    }
+    if let Some(length) = &options.max_line_length {
+        if let Err(err) = handler.set_max_line_length(length) {
+            logger::error(&format!("Invalid max line length: {}", err));
+            process::exit(2);
+        }
+    }
+    let max_length = handler.max_line_length();
+    if let Some(path) = &options.script {
+        let file = match File::open(path) {
+            Ok(file) => file,
             Err(err) => {
-                eprintln!("Error occurred while accepting stdin {}", err)
+                logger::error(&format!("Could not open script {}: {}", path, err));
+                process::exit(2);
             }
+        };
+        if !run_lines(&mut handler, bounded_lines(io::BufReader::new(file), max_length), options.strict) {
+            return;
         }
     }
+    if options.stdin {
+        run_lines(&mut handler, bounded_lines(io::stdin().lock(), max_length), false);
+    }
 }
@@ -2,17 +2,21 @@
  * Copyright (c) Joseph Prichard 2022.
  */
 
+use crate::logger;
+
 #[derive(Copy, Clone, Debug)]
 pub struct Run {
     max_depth: u32,
     hits: u32,
     misses: u32,
+    nodes: u64,
+    evals: u64,
     time_taken: u128,
 }
 
 impl Run {
-    pub fn new(max_depth: u32, hits: u32, misses: u32, time_taken: u128) -> Self {
-        Self { max_depth, hits, misses, time_taken }
+    pub fn new(max_depth: u32, hits: u32, misses: u32, nodes: u64, evals: u64, time_taken: u128) -> Self {
+        Self { max_depth, hits, misses, nodes, evals, time_taken }
     }
 }
 
@@ -36,14 +40,32 @@ impl Profiler {
         let len = self.runs.len();
         for run in self.runs.iter() {
             let debug_output = format!(
-                "Finished analysis, max_depth: {}, hits: {}, misses: {}, time_taken: {} ms",
-                run.max_depth, run.hits, run.misses, run.time_taken
+                "Finished analysis, max_depth: {}, hits: {}, misses: {}, nodes: {}, evals: {}, time_taken: {} ms",
+                run.max_depth, run.hits, run.misses, run.nodes, run.evals, run.time_taken
             );
-            eprintln!("{}", debug_output);
+            logger::info(&debug_output);
             total_time += run.time_taken;
         }
         let avg_time = if len > 0 { total_time / len as u128 } else { 0 };
-        eprintln!("Total time: {} ms", total_time);
-        eprintln!("Average time {} ms", avg_time)
+        logger::info(&format!("Total time: {} ms", total_time));
+        logger::info(&format!("Average time {} ms", avg_time))
+    }
+
+    // the runs as csv with a header row, for loading into other tools
+    pub fn export_runs(&self) -> String {
+        let mut csv = String::from("max_depth,hits,misses,nodes,evals,time_taken");
+        for run in self.runs.iter() {
+            csv.push_str(&format!(
+                "\n{},{},{},{},{},{}",
+                run.max_depth, run.hits, run.misses, run.nodes, run.evals, run.time_taken
+            ));
+        }
+        csv
     }
-}
\ No newline at end of file
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,6 +3,7 @@
  */
 
 use std::fmt;
+use std::str::FromStr;
 use crate::errors::{ParseError, ParseResult};
 
 #[derive(Clone, Copy, PartialEq)]
@@ -22,63 +23,162 @@ impl Tile {
         Self { row, col }
     }
 
-    pub fn from_str(str: &str) -> ParseResult<Self> {
+    pub fn index(&self) -> usize {
+        (self.row * 8 + self.col) as usize
+    }
+
+    pub fn in_bounds(&self) -> bool {
+        self.row >= 0 && self.col >= 0 && self.row < 8 && self.col < 8
+    }
+
+    // steps the tile by a row and col delta, returning none if the result is off the board
+    pub fn offset(&self, dr: i8, dc: i8) -> Option<Tile> {
+        let tile = Self::new(self.row + dr, self.col + dc);
+        if tile.in_bounds() { Some(tile) } else { None }
+    }
+
+    pub fn is_corner(&self) -> bool {
+        (self.row == 0 || self.row == 7) && (self.col == 0 || self.col == 7)
+    }
+}
+
+impl FromStr for Tile {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> ParseResult<Self> {
         // check if the tile is the right size
         if str.len() != 2 {
-            return Err(ParseError::new("Tile notation must be 2 characters long"))
+            return Err(ParseError::InvalidTile("Tile notation must be 2 characters long".into()))
         }
         let mut chars = str.chars();
         let c1 = chars.next().unwrap_or_default();
         let c2 = chars.next().unwrap_or_default();
-        // convert first char into column and convert second char into row
-        let col = ((c1 as u8) - ('a' as u8)) as i8;
+        // convert first char into column and convert second char into row, chars below 'a' can't be subtracted
+        let col = if c1.is_ascii_lowercase() { (c1 as u8 - b'a') as i8 } else { -1 };
         let row = c2.to_digit(10).unwrap_or_default() as i8 - 1;
         // check if the each char is within the acceptable range
         if row < 0 || col < 0 || row > 7 || col > 7 {
-            return Err(ParseError::new("Tile row col pair must be between a1 and h7"))
+            return Err(ParseError::InvalidTile("Tile row col pair must be between a1 and h8".into()))
         }
         Ok(Self { row, col })
     }
+}
 
-    pub fn in_bounds(&self) -> bool {
-        self.row >= 0 && self.col >= 0 && self.row < 8 && self.col < 8
+impl TryFrom<&str> for Tile {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> ParseResult<Self> {
+        Self::from_str(str)
+    }
+}
+
+// indices past 63 give a tile off the board, as with from_index
+impl From<usize> for Tile {
+    fn from(index: usize) -> Self {
+        Self::from_index(index)
     }
 }
 
 impl fmt::Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let c = (self.col as u8 + ('a' as u8)) as char;
+        let c = (self.col as u8 + b'a') as char;
         let r = (self.row + 1).to_string();
         write!(f, "{}{}", c, r)
     }
 }
 
+// the conventions a tile can be written in, algebraic is column letter then row number like d3, row first is the
+// reverse like 3d, and numeric is the zero based row and column separated by a comma like 2,3
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CoordStyle {
+    #[default]
+    Algebraic,
+    RowFirst,
+    Numeric,
+}
+
+impl CoordStyle {
+    pub fn format(self, tile: Tile) -> String {
+        match self {
+            CoordStyle::Algebraic => tile.to_string(),
+            CoordStyle::RowFirst => format!("{}{}", tile.row + 1, (tile.col as u8 + b'a') as char),
+            CoordStyle::Numeric => format!("{},{}", tile.row, tile.col),
+        }
+    }
+
+    pub fn parse(self, str: &str) -> ParseResult<Tile> {
+        match self {
+            CoordStyle::Algebraic => Tile::from_str(str),
+            // the same two characters as algebraic notation in the opposite order
+            CoordStyle::RowFirst => Tile::from_str(&str.chars().rev().collect::<String>()),
+            CoordStyle::Numeric => {
+                let coords = str.split_once(',')
+                    .and_then(|(row, col)| Some((row.parse::<i8>().ok()?, col.parse::<i8>().ok()?)));
+                match coords {
+                    Some((row, col)) if Tile::new(row, col).in_bounds() => Ok(Tile::new(row, col)),
+                    _ => Err(ParseError::InvalidTile("Tile row col pair must be between 0,0 and 7,7".into()))
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RankedTile {
     pub tile: Tile,
     pub heuristic: f32,
+    // the depth the position after the move was searched to when the heuristic was found
+    pub depth: u32,
 }
 
 impl RankedTile {
-    pub fn new(tile: Tile, heuristic: f32) -> Self {
-        Self { tile, heuristic }
+    pub fn new(tile: Tile, heuristic: f32, depth: u32) -> Self {
+        Self { tile, heuristic, depth }
     }
 
-    pub fn from_option(tile: Option<Tile>, heuristic: f32) -> Option<Self> {
-        if let Some(t) = tile {
-            Some(Self::new(t, heuristic))
-        } else {
-            None
-        }
+    pub fn from_option(tile: Option<Tile>, heuristic: f32, depth: u32) -> Option<Self> {
+        tile.map(|t| Self::new(t, heuristic, depth))
+    }
+}
+
+impl RankedTile {
+    pub fn format(&self, style: CoordStyle) -> String {
+        format!("Disc: {}, Heuristic: {}, Depth: {}", style.format(self.tile), self.heuristic, self.depth)
     }
 }
 
 impl fmt::Display for RankedTile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Disc: {}, Heuristic: {}", self.tile, self.heuristic)
+        write!(f, "{}", self.format(CoordStyle::Algebraic))
     }
 }
 
+// tiles are written as their notation like "d3" rather than as a row and col, to match what the engine reads and writes
+#[cfg(feature = "serde")]
+impl serde::Serialize for Tile {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Tile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let notation = String::deserialize(deserializer)?;
+        notation.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// reads a game transcript, the moves written one after another in either case like "f5d6c3", with passes left out
+pub fn parse_transcript(transcript: &str) -> ParseResult<Vec<Tile>> {
+    if !transcript.is_ascii() || !transcript.len().is_multiple_of(2) {
+        return Err(ParseError::InvalidNotation("Transcript must be a sequence of 2 character moves".into()))
+    }
+    let transcript = transcript.to_ascii_lowercase();
+    (0..transcript.len()).step_by(2).map(|i| Tile::from_str(&transcript[i..i + 2])).collect()
+}
+
 pub static TILES: [Tile; 64] = tiles();
 
 pub const fn tiles() -> [Tile; 64] {
@@ -89,4 +189,104 @@ pub const fn tiles() -> [Tile; 64] {
         i += 1;
     }
     tiles
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use proptest::prelude::*;
+    use crate::errors::ParseError;
+    use crate::tile::{parse_transcript, CoordStyle, Tile, TILES};
+
+    proptest! {
+        #[test]
+        fn test_from_str_never_panics(str in "\\PC{0,4}") {
+            let _ = Tile::from_str(&str);
+        }
+
+        #[test]
+        fn test_from_str_ascii_never_panics(str in "[ -~]{0,3}") {
+            let _ = Tile::from_str(&str);
+            let _ = CoordStyle::RowFirst.parse(&str);
+            let _ = CoordStyle::Numeric.parse(&str);
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert!(Tile::from_str("d3").unwrap() == Tile::new(2, 3));
+        assert!(Tile::from_str("h8").unwrap() == Tile::new(7, 7));
+        assert!(Tile::from_str("A1").is_err());
+        assert!(Tile::from_str("i1").is_err());
+        assert!(Tile::from_str("a9").is_err());
+        assert!(Tile::from_str("a").is_err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let tile: Tile = "d3".try_into().unwrap();
+        assert!(tile == Tile::new(2, 3));
+        assert!(matches!(Tile::try_from("d9"), Err(ParseError::InvalidTile(_))));
+        assert!(matches!(Tile::try_from(""), Err(ParseError::InvalidTile(_))));
+
+        for (i, tile) in TILES.iter().enumerate() {
+            assert!(Tile::from(i) == *tile);
+            assert_eq!(Tile::from(i).index(), i);
+        }
+        let tile: Tile = 63usize.into();
+        assert!(tile == Tile::new(7, 7));
+        assert!(!Tile::from(64).in_bounds());
+    }
+
+    #[test]
+    fn test_parse_transcript() {
+        let moves = parse_transcript("F5d6C3").unwrap();
+        assert_eq!(moves.iter().map(|tile| tile.to_string()).collect::<Vec<String>>(), ["f5", "d6", "c3"]);
+        assert!(parse_transcript("").unwrap().is_empty());
+        assert!(matches!(parse_transcript("f5d"), Err(ParseError::InvalidNotation(_))));
+        assert!(matches!(parse_transcript("f5z9"), Err(ParseError::InvalidTile(_))));
+        assert!(matches!(parse_transcript("f5é"), Err(ParseError::InvalidNotation(_))));
+    }
+
+    #[test]
+    fn test_coord_styles() {
+        let tile = Tile::new(2, 3);
+        assert_eq!(CoordStyle::Algebraic.format(tile), "d3");
+        assert_eq!(CoordStyle::RowFirst.format(tile), "3d");
+        assert_eq!(CoordStyle::Numeric.format(tile), "2,3");
+
+        for style in [CoordStyle::Algebraic, CoordStyle::RowFirst, CoordStyle::Numeric] {
+            for tile in TILES {
+                assert!(style.parse(&style.format(tile)).unwrap() == tile);
+            }
+        }
+
+        assert!(CoordStyle::RowFirst.parse("d3").is_err());
+        assert!(CoordStyle::RowFirst.parse("9a").is_err());
+        assert!(CoordStyle::Numeric.parse("8,0").is_err());
+        assert!(CoordStyle::Numeric.parse("-1,0").is_err());
+        assert!(CoordStyle::Numeric.parse("2 3").is_err());
+    }
+
+    #[test]
+    fn test_offset() {
+        assert!(Tile::new(3, 4).offset(-1, 1) == Some(Tile::new(2, 5)));
+        assert!(Tile::new(7, 7).offset(-7, -7) == Some(Tile::new(0, 0)));
+        assert!(Tile::new(0, 3).offset(-1, 0).is_none());
+        assert!(Tile::new(4, 7).offset(1, 1).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ranked_tile_json() {
+        use crate::tile::RankedTile;
+
+        let ranked = RankedTile::new(Tile::new(2, 3), 12f32, 5);
+        let json = serde_json::to_string(&ranked).unwrap();
+        assert_eq!(json, r#"{"tile":"d3","heuristic":12.0,"depth":5}"#);
+
+        let parsed: RankedTile = serde_json::from_str(&json).unwrap();
+        assert!(parsed.tile == ranked.tile && parsed.heuristic == ranked.heuristic && parsed.depth == ranked.depth);
+        assert!(serde_json::from_str::<RankedTile>(r#"{"tile":"z9","heuristic":0.0,"depth":1}"#).is_err());
+    }
+}